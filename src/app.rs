@@ -1,215 +1,217 @@
-use egui::{Pos2, Rect, Vec2, Stroke, Color32};
-use std::{
-    collections::HashMap,
-    ops::{Add, AddAssign},
-    time::Duration, fs::FileType,
-};
+use egui::{Color32, Key, KeyboardShortcut, Modifiers, Pos2, Rect, Stroke, Vec2};
+use std::{collections::HashMap, fs::FileType, time::Duration};
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
-use web_sys::{Url, Window};
+#[cfg(target_arch = "wasm32")]
+use web_sys::Url;
 
-use image::{RgbImage, Rgb, DynamicImage};
-use image::imageops::FilterType;
+use crate::history::{EditCommand, History, Properties};
+use crate::library::{self, Library};
+use crate::map::{
+    Course, Direction, GameObject, GolfMap, Point, Surface, Timing, ARC_RADII, ARC_SWEEPS,
+    ARM_LENGTHS, BUMPER_BOUNCES, BUMPER_RADII, CELL_SIZES, GRID_SIZES, RAIL_STEPS,
+    RAIL_THICKNESSES,
+};
+use crate::map_code::MapCodeError;
+use crate::physics::{GolfBall, Pos, AIM_REACH, AIM_STRENGTH, PHYSICS_DT};
+use crate::players::{self, Player, TurnOrder, MAX_PLAYERS};
+use crate::render;
+use crate::replay::{self, PersonalBest, Replay, ReplayViewer, Shot};
+use crate::solver::{PlannedShot, Search, Solution, SolverSettings};
+
+/// longest stretch of time simulated in a single frame, so a stalled tab doesn't try to catch up on minutes of physics
+const MAX_FRAME_TIME: f32 = 0.25;
+/// shots the solver tries each frame while it is searching, a few milliseconds' worth
+const SEARCH_SHOTS_PER_FRAME: u32 = 20;
+
+/// a quick look a couple of strokes ahead, as a hint is wanted straight away
+const HINT_SEARCH: SolverSettings = SolverSettings {
+    max_strokes: 2,
+    angles: 36,
+    powers: 6,
+    beam_width: 4,
+};
 
-const CHARS: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+/// what a search being run by the solver is for
+enum SearchFor {
+    Par,
+    /// the next shot for the player with this index
+    Hint {
+        player: usize,
+    },
+}
 
 #[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, PartialEq, Eq, EnumIter)]
 enum EditOption {
     PlayGame,
     EditMap(GameObject),
     Delete,
+    Select,
 }
 
-#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, PartialEq, Eq, EnumIter)]
-enum GameObject {
-    Hole(Point),
-    Wall { a: Point, b: Point },
-    Start(Point),
-    Height { a: Point, b: Point, height: i32 },
+/// the grid new rail ends are placed on
+#[derive(
+    serde::Deserialize, serde::Serialize, Clone, Copy, Debug, Default, PartialEq, Eq, EnumIter,
+)]
+enum Snap {
+    #[default]
+    HalfCell,
+    Free,
 }
 
-impl GameObject {
-    fn symbol(&self) -> String {
-        match &self {
-            GameObject::Hole(_) => "H".to_string(),
-            GameObject::Wall { .. } => "W".to_string(),
-            GameObject::Start(_) => "S".to_string(),
-            GameObject::Height { .. } => "H".to_string(),
-        }
-    }
-
-    fn point(&self) -> Option<Point> {
-        match &self {
-            GameObject::Hole(p) => Some(*p),
-            GameObject::Wall { .. } => None,
-            GameObject::Start(p) => Some(*p),
-            GameObject::Height { .. } => None,
+impl Snap {
+    fn name(&self) -> &'static str {
+        match self {
+            Snap::HalfCell => "Half cell",
+            Snap::Free => "Free",
         }
     }
 
-    fn get_points(&self) -> Vec<Point> {
-        match &self {
-            GameObject::Hole(p) => vec![*p],
-            GameObject::Wall { a, b } => vec![*a, *b],
-            GameObject::Start(p) => vec![*p],
-            GameObject::Height { a, b, .. } => vec![*a, *b],
+    /// rail steps between each place an end can go
+    fn steps(&self) -> i32 {
+        match self {
+            Snap::HalfCell => RAIL_STEPS / 2,
+            Snap::Free => 1,
         }
     }
 }
 
-#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
-#[serde(default)]
-struct Point {
-    x: i32,
-    y: i32,
+/// the part of an object being dragged with the select tool
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Handle {
+    Whole,
+    A,
+    B,
 }
 
-impl Default for Point {
-    fn default() -> Self {
-        Self { x: 0, y: 0 }
-    }
+/// an object being dragged with the select tool
+#[derive(Clone, Copy, Debug)]
+struct Drag {
+    /// index of the object in the map's objects
+    index: usize,
+    /// the object as it was before the drag started
+    before: GameObject,
+    handle: Handle,
+    /// the cell the drag started from
+    grab: Point,
+}
 
+/// Something as it was before a widget in the side panel started changing it.
+enum Before {
+    /// the hole's settings
+    Properties(Properties),
+    /// one of the objects on the green
+    Object { index: usize, object: GameObject },
 }
 
+/// where the web build is hosted, used for share links from the native app
+const SHARE_URL: &str = "https://ollielynas.github.io/mini-golf/";
 
-impl Point {
-    fn new(x: i32, y: i32) -> Self {
-        Self { x, y }
+/// The map code passed in the page address as `?map=<code>` or `#<code>`, if there is one.
+#[cfg(target_arch = "wasm32")]
+fn shared_map_code() -> Option<String> {
+    let href = web_sys::window()?.location().href().ok()?;
+    let url = Url::new(&href).ok()?;
+    if let Some(code) = url.search_params().get("map") {
+        return Some(code);
     }
+    let hash = url.hash();
+    let code = hash.trim_start_matches('#');
+    let code = code.strip_prefix("map=").unwrap_or(code);
+    // `#dev` is how the service worker cache gets skipped during development, it isn't a map
+    (!code.is_empty() && code != "dev").then(|| code.to_owned())
 }
 
-#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, PartialEq)]
-#[serde(default)]
-struct GolfBall {
-    pos: Pos,
-    vel: Pos,
-    shoot: bool,
+#[cfg(not(target_arch = "wasm32"))]
+fn shared_map_code() -> Option<String> {
+    None
 }
 
-impl Default for GolfBall {
-    fn default() -> Self {
-        Self {
-            pos: Pos::default(),
-            vel: Pos::default(),
-            shoot: false,
+/// A link that opens the web build straight into the map with the given code.
+fn share_link(code: &str) -> String {
+    #[cfg(target_arch = "wasm32")]
+    if let Some(location) = web_sys::window().map(|w| w.location()) {
+        if let (Ok(origin), Ok(path)) = (location.origin(), location.pathname()) {
+            return format!("{origin}{path}?map={code}");
         }
     }
+    format!("{SHARE_URL}?map={code}")
 }
 
-impl Add for Pos {
-    type Output = Self;
+/// how long the result of a hole stays up before the next hole is loaded (seconds)
+const NEXT_HOLE_DELAY: f64 = 2.5;
 
-    fn add(self, rhs: Self) -> Self::Output {
-        Self {
-            x: self.x + rhs.x,
-            y: self.y + rhs.y,
-        }
+/// the name of a score relative to par, like "Birdie" or "Double bogey"
+fn score_name(strokes: u32, par: u32) -> String {
+    if strokes == 1 {
+        return "Hole in one!".to_owned();
     }
-}
-
-impl AddAssign for Pos {
-    fn add_assign(&mut self, rhs: Self) {
-        self.x += rhs.x;
-        self.y += rhs.y;
+    match strokes as i32 - par as i32 {
+        i32::MIN..=-4 => "Condor".to_owned(),
+        -3 => "Albatross".to_owned(),
+        -2 => "Eagle".to_owned(),
+        -1 => "Birdie".to_owned(),
+        0 => "Par".to_owned(),
+        1 => "Bogey".to_owned(),
+        2 => "Double bogey".to_owned(),
+        3 => "Triple bogey".to_owned(),
+        over => format!("+{over}"),
     }
 }
 
-impl GolfBall {
-    fn update_pos(&mut self, map: &GolfMap, delta: f32) {
-        let mut new_pos = self.pos.clone();
-        self.vel.x *= 0.98;
-        self.vel.y *= 0.98;
-        new_pos += self.vel.with_delta(delta);
-
-
-        let mut top = new_pos;
-        top.y-=8.0;
-        let mut bottom = new_pos;
-        bottom.y+=8.0;
-        let mut left = new_pos;
-        left.x-=8.0;
-        let mut right = new_pos;
-        right.x+=8.0;
-
-        // let pix = map.heightmap.get_pixel(((self.pos.x*5.0)/10.0) as u32, ((self.pos.y*5.0)/10.0) as u32);
-        // self.vel.x += (pix[1] as f32 - 125.0);
-        // self.vel.y += (pix[2] as f32 - 125.0);
-
-        if self.vel.y < 0.0 {
-            if let Some(p) = map.get_point(&top.to_point()) {
-                if matches!(p, GameObject::Wall {..}) {
-                    self.vel.y*=-1.0;
-                }
-            }
-        }else {
-            if let Some(p) = map.get_point(&bottom.to_point()) {
-                if matches!(p, GameObject::Wall {..}) {
-                    self.vel.y*=-1.0;
-                }
-            }
-        }
-
-        if self.vel.x < 0.0 {
-            if let Some(p) = map.get_point(&left.to_point()) {
-                if matches!(p, GameObject::Wall {..}) {
-                    self.vel.x*=-1.0;
-                }
-            }
-        }else {
-            if let Some(p) = map.get_point(&right.to_point()) {
-                if matches!(p, GameObject::Wall {..}) {
-                    self.vel.x*=-1.0;
-                }
-            }
-        }
-
-        if new_pos.x < 4.0 || new_pos.x > 400.0-6.0 {
-            self.vel.x *= -1.0;
+/// What the rail, bumper or curved wall tool puts down with a click at `spot` on the rail grid.
+/// Rails and curved walls take two clicks, this is None for the first as it only marks where they start.
+fn place_on_rail_grid(tool: GameObject, spot: Point) -> Option<GameObject> {
+    match tool {
+        GameObject::Rail { a, thickness, .. } if a != (Point { x: -1, y: -1 }) && a != spot => {
+            Some(GameObject::Rail {
+                a,
+                b: spot,
+                thickness,
+            })
         }
-        if new_pos.y < 4.0 || new_pos.y > 400.0-6.0 {
-            self.vel.y *= -1.0;
+        GameObject::Bumper { radius, bounce, .. } => Some(GameObject::Bumper {
+            centre: spot,
+            radius,
+            bounce,
+        }),
+        // the second click is on the curve, where it starts going round from
+        GameObject::Arc {
+            centre,
+            sweep,
+            thickness,
+            ..
+        } if centre != (Point { x: -1, y: -1 }) && centre != spot => {
+            let (dx, dy) = ((spot.x - centre.x) as f32, (spot.y - centre.y) as f32);
+            Some(GameObject::Arc {
+                centre,
+                radius: (dx.hypot(dy).round() as i32).clamp(*ARC_RADII.start(), *ARC_RADII.end()),
+                from: (dy.atan2(dx).to_degrees().round() as i32).rem_euclid(360),
+                sweep,
+                thickness,
+            })
         }
-
-        self.pos += self.vel.with_delta(delta);
+        _ => None,
     }
 }
 
-#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, PartialEq)]
-#[serde(default)]
-struct Pos {
-    x: f32,
-    y: f32,
-}
-
-impl Pos {
-    // impl into
-    fn new<T: Into<f32>>(x: T, y: T) -> Self {
-        Self {
-            x: x.into(),
-            y: y.into(),
-        }
-    }
-
-    fn with_delta(&self, delta: f32) -> Self {
-        Self {
-            x: self.x * delta,
-            y: self.y * delta,
-        }
-    }
-
-    fn velocity(&self) -> f32 {
-        (self.x.powi(2) + self.y.powi(2)).sqrt()
-    }
-
-    fn to_point(&self) -> Point {
-        Point { x: (self.x/20.0) as i32, y: (self.y/20.0) as i32 }
+/// what a teleporter does to the direction of the ball
+fn turn_name(quarter_turns: i32) -> &'static str {
+    match quarter_turns.rem_euclid(4) {
+        0 => "Straight on",
+        1 => "Turn right",
+        2 => "Turn around",
+        _ => "Turn left",
     }
 }
 
-impl Default for Pos {
-    fn default() -> Self {
-        Self { x: 0.0, y: 0.0 }
+/// a score relative to par the way a scorecard shows it: "E", "+2", "-1"
+fn to_par_text(diff: i32) -> String {
+    match diff {
+        0 => "E".to_owned(),
+        d if d > 0 => format!("+{d}"),
+        d => format!("{d}"),
     }
 }
 
@@ -232,273 +234,737 @@ impl Into<Pos2> for Pos {
 
 #[derive(serde::Deserialize, serde::Serialize)]
 #[serde(default)]
-struct GolfMap {
-    objects: Vec<GameObject>,
-    map: HashMap<Point, GameObject>,
+pub struct App {
+    course: Course,
+    /// the one map saved by versions from before courses, moved into the first hole when it is loaded
+    #[serde(rename = "map", skip_serializing)]
+    legacy_map: Option<GolfMap>,
+    /// index of the hole being played in `course`
+    hole: usize,
+    /// everyone taking turns, each with their own ball and scores
+    players: Vec<Player>,
+    /// the ball, strokes and scorecard saved by versions from before there were players, given to the first one
+    #[serde(rename = "ball", skip_serializing)]
+    legacy_ball: Option<GolfBall>,
+    #[serde(rename = "strokes", skip_serializing)]
+    legacy_strokes: Option<u32>,
+    #[serde(rename = "scores", skip_serializing)]
+    legacy_scores: Option<Vec<Option<u32>>>,
+    /// index of the player whose turn it is in `players`
+    current: usize,
+    turn_order: TurnOrder,
+    /// whether the balls knock each other about, or roll through each other
+    balls_collide: bool,
+    /// whether a shot is still rolling, the next player is picked once every ball stops
     #[serde(skip)]
-    heightmap: RgbImage,
-}
-
-fn char_i(index:i32)->String {
-    let mut s = String::new();
-    s.push(CHARS.chars().nth(index as usize).unwrap_or('~'));
-    s
+    shot_in_play: bool,
+    show_scorecard: bool,
+    #[serde(skip)]
+    next_hole_at: Option<f64>,
+    /// time that has passed but not been simulated yet, always less than one `PHYSICS_DT`
+    #[serde(skip)]
+    accumulator: f32,
+    /// physics steps since the hole was started, moving obstacles go by this
+    #[serde(skip)]
+    tick: u32,
+    edit: EditOption,
+    reset: bool,
+    slider: i32,
+    /// the ground the surface tool lays down
+    surface: Surface,
+    /// loop time and phase given to new moving obstacles
+    timing: Timing,
+    /// how far new windmills reach, in cells
+    arm: i32,
+    /// quarter turns clockwise new teleporters turn the ball
+    turn: i32,
+    /// the way new boosters and conveyors point
+    direction: Direction,
+    /// how thick new rails are, in rail steps
+    rail_thickness: i32,
+    snap: Snap,
+    /// how big new bumpers are from their middle to their edge, in rail steps
+    bumper_radius: i32,
+    /// how much of its speed the ball keeps bouncing off new bumpers, in percent
+    bumper_bounce: i32,
+    /// how far round new curved walls go, in degrees
+    arc_sweep: i32,
+    scale: f32,
+    text: String,
+    /// why the code in `text` couldn't be loaded
+    #[serde(skip)]
+    text_error: Option<MapCodeError>,
+    /// edits made to the current hole, forgotten when another hole is loaded
+    #[serde(skip)]
+    history: History,
+    #[serde(skip)]
+    drag: Option<Drag>,
+    /// the object last picked up with the select tool, its settings are shown in the side panel
+    #[serde(skip)]
+    selected: Option<usize>,
+    /// what the widget with this id is changing as it was before it started,
+    /// so a drag or a bit of typing is undone all at once
+    #[serde(skip)]
+    editing: Option<(egui::Id, Before)>,
+    /// saved separately under `library::LIBRARY_KEY`
+    #[serde(skip)]
+    library: Library,
+    show_library: bool,
+    /// name put on maps saved to the library
+    author: String,
+    /// every shot taken on the hole being played, started again with the hole
+    #[serde(skip)]
+    recording: Replay,
+    show_replay: bool,
+    /// the replay being watched in the replay window
+    #[serde(skip)]
+    viewer: Option<ReplayViewer>,
+    /// a replay code pasted in to watch
+    #[serde(skip)]
+    replay_text: String,
+    /// why the replay couldn't be watched
+    #[serde(skip)]
+    replay_error: Option<MapCodeError>,
+    /// the best finish on each map played, keyed by its map code
+    personal_bests: HashMap<String, PersonalBest>,
+    show_ghost: bool,
+    /// the personal best for the hole being played, kept in time with it
+    #[serde(skip)]
+    ghost: Option<ReplayViewer>,
+    /// what the solver found for the hole, `Some(None)` if it couldn't find a way in
+    #[serde(skip)]
+    solution: Option<Option<Solution>>,
+    /// the solver's search, run a bit each frame so the game carries on while it looks
+    #[serde(skip)]
+    search: Option<(Search, SearchFor)>,
+    /// strokes added for each hint, with none they are only counted on the scorecard
+    hint_penalty: u32,
+    /// the shot suggested for the current player, `Some(None)` if the solver couldn't find one
+    #[serde(skip)]
+    hint: Option<Option<PlannedShot>>,
 }
 
-fn i_char(c:char)->i32 {
-    CHARS.chars().position(|x| x == c).unwrap_or(0) as i32
+impl Default for App {
+    fn default() -> Self {
+        let course = Course::default();
+        Self {
+            players: vec![Player::new("Player 1".to_owned(), course.holes.len())],
+            course,
+            legacy_map: None,
+            hole: 0,
+            legacy_ball: None,
+            legacy_strokes: None,
+            legacy_scores: None,
+            current: 0,
+            turn_order: TurnOrder::default(),
+            balls_collide: true,
+            shot_in_play: false,
+            show_scorecard: false,
+            next_hole_at: None,
+            accumulator: 0.0,
+            tick: 0,
+            edit: EditOption::PlayGame,
+            reset: true,
+            slider: 0,
+            surface: Surface::default(),
+            timing: Timing::default(),
+            arm: 2,
+            turn: 0,
+            direction: Direction::default(),
+            rail_thickness: 2,
+            snap: Snap::default(),
+            bumper_radius: 5,
+            bumper_bounce: 150,
+            arc_sweep: 90,
+            scale: 3.0,
+            text: String::new(),
+            text_error: None,
+            history: History::default(),
+            drag: None,
+            selected: None,
+            editing: None,
+            library: Library::default(),
+            show_library: false,
+            author: String::new(),
+            recording: Replay::default(),
+            show_replay: false,
+            viewer: None,
+            replay_text: String::new(),
+            replay_error: None,
+            personal_bests: HashMap::new(),
+            show_ghost: true,
+            ghost: None,
+            solution: None,
+            search: None,
+            hint_penalty: 1,
+            hint: None,
+        }
+    }
 }
 
-impl GolfMap {
-
+impl App {
+    /// Called once before the first frame.
+    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        // This is also where you can customize the look and feel of egui using
+        // `cc.egui_ctx.set_visuals` and `cc.egui_ctx.set_fonts`.
+        // set to continuous updates
+        // Load previous app state (if any).
+        // Note that you must enable the `persistence` feature for this to work.
 
-    fn to_text(&self) -> String {
-        let mut s = String::new();
+        let mut new: Self = Default::default();
+        let mut restored = false;
 
-        for i in &self.objects {
-            s=match i {
-                GameObject::Wall { a:Point{x:-1,y:-1}, b:Point{x:-1,y:-1} } => {
-                    "".to_owned()
+        if let Some(storage) = cc.storage {
+            if let Some(saved) = eframe::get_value::<Self>(storage, eframe::APP_KEY) {
+                // carry on where the last session left off (balls and stroke counts included),
+                // only the lookup tables that are not saved need rebuilding
+                new = saved;
+                if new.course.holes.is_empty() {
+                    new.course.holes.push(GolfMap::default());
+                }
+                if let Some(map) = new.legacy_map.take() {
+                    new.course.holes[0] = map;
                 }
-                GameObject::Wall { a, b } => {
-                    format!("a{}{}{}{}", char_i(a.x), char_i(a.y), char_i(b.x), char_i(b.y))
+                new.hole = new.hole.min(new.course.holes.len() - 1);
+                if new.players.is_empty() {
+                    new.players
+                        .push(Player::new("Player 1".to_owned(), new.course.holes.len()));
                 }
-                GameObject::Hole(pos) => {
-                    format!("b{}{}", char_i(pos.x), char_i(pos.y))
+                if let Some(ball) = new.legacy_ball.take() {
+                    new.players[0].take_over(ball, new.legacy_strokes.take().unwrap_or(0));
                 }
-                GameObject::Height { a, b, height } => {
-                    format!("c{}{}{}{}{}", char_i(a.x), char_i(a.y), char_i(b.x), char_i(b.y), char_i(*height))
+                if let Some(scores) = new.legacy_scores.take() {
+                    new.players[0].scores = scores;
                 }
-                GameObject::Start(pos) => {
-                    format!("d{}{}", char_i(pos.x), char_i(pos.y))
+                new.current = new.current.min(new.players.len() - 1);
+                for player in &mut new.players {
+                    player.resize_round(new.course.holes.len());
                 }
+                // bests are looked up by map code, so codes saved in an older format are written out again
+                new.personal_bests = std::mem::take(&mut new.personal_bests)
+                    .into_iter()
+                    .map(|(code, best)| {
+                        let code = GolfMap::from_text(&code).map_or(code, |map| map.to_text());
+                        (code, best)
+                    })
+                    .collect();
+                let map = new.map_mut();
+                map.update_hashmap();
+                map.update_heightmap();
+                new.text = new.map().to_text();
+                // shots taken before the last session ended weren't kept, the replay starts from here
+                new.start_recording();
+                restored = true;
+            }
+            new.library = Library::load(storage);
+        }
+
+        if let Some(code) = shared_map_code() {
+            new.open_shared_map(&code);
+        } else if !restored {
+            new.reset();
+        }
+        new
+    }
 
+    /// Jumps to the map from a share link, adding it to the end of the course
+    /// unless the course already has a hole with exactly that layout.
+    fn open_shared_map(&mut self, code: &str) {
+        let mut map = match GolfMap::from_text(code) {
+            Ok(map) => map,
+            Err(error) => {
+                log::warn!("couldn't open the shared map: {error}");
+                self.reset();
+                return;
+            }
+        };
+        let code = map.to_text();
+        let hole = match self.course.holes.iter().position(|h| h.to_text() == code) {
+            Some(i) => i,
+            None => {
+                map.name = "Shared hole".to_owned();
+                self.course.holes.push(map);
+                for player in &mut self.players {
+                    player.resize_round(self.course.holes.len());
+                }
+                self.course.holes.len() - 1
+            }
+        };
+        self.go_to_hole(hole);
+    }
 
-            }+s.as_str();
+    /// Replaces the current hole with the map in the code box, if the code reads.
+    fn load_code(&mut self) {
+        match GolfMap::from_text(&self.text) {
+            Ok(mut map) => {
+                map.name = self.map().name.clone();
+                self.replace_map(map);
+            }
+            Err(error) => self.text_error = Some(error),
         }
-        s
     }
 
-    fn from_text(&mut self, text: String) {
-        self.map.clear();
-        self.objects.clear();
-        let mut i = 0;
-        let chars: Vec<i32> = text.chars().map(|x| i_char(x)).collect();
-        while i < chars.len() {
-            let c = chars[i];
+    /// Swaps the current hole for another map, as an edit that can be undone.
+    fn replace_map(&mut self, map: GolfMap) {
+        self.drag = None;
+        self.edit_map(EditCommand::replace(self.map(), &map));
+    }
 
-            match c  {
-                0 => if chars.len() > i+5 {
-                    
-                    let a = Point { x: chars[i+1], y: chars[i+2] };
-                    let b = Point { x: chars[i+3], y: chars[i+4] };
-                    self.add_object(GameObject::Wall { a, b });
-                    i+=5;
-                }
-                1 => if chars.len() > i+3 {
-                    let pos = Point { x: chars[i+1], y: chars[i+2] };
-                    self.add_object(GameObject::Hole(pos));
-                    i+=3;
-                }
-                2 => if chars.len() > i+6 {
-                    let a = Point { x: chars[i+1], y: chars[i+2] };
-                    let b = Point { x: chars[i+3], y: chars[i+4] };
-                    let height = chars[i+5];
-                    self.add_object(GameObject::Height { a, b, height });
-                    i+=6;
-                }
-                3 => if chars.len() > i+3 {
-                    let pos = Point { x: chars[i+1], y: chars[i+2] };
-                    self.add_object(GameObject::Start(pos));
-                    i+=3;
-                }
-                _ => {}
+    /// Opens a map from the library in place of the current hole.
+    fn open_saved_map(&mut self, index: usize) {
+        let Some(saved) = self.library.maps.get(index) else {
+            return;
+        };
+        match GolfMap::from_text(&saved.code) {
+            Ok(mut map) => {
+                map.name = saved.title.clone();
+                self.replace_map(map);
             }
+            Err(error) => log::warn!("couldn't open {} from the library: {error}", saved.title),
         }
     }
 
-    fn add_object(&mut self, obj: GameObject) {
-        self.objects.push(obj);
-        self.update_hashmap();
+    fn map(&self) -> &GolfMap {
+        &self.course.holes[self.hole]
     }
-    fn get_point(&self, point: &Point) -> Option<&GameObject> {
-        self.map.get(point)
+
+    fn map_mut(&mut self) -> &mut GolfMap {
+        &mut self.course.holes[self.hole]
     }
 
-    fn update_heightmap(&mut self) {
-        let mut image: RgbImage = RgbImage::from_pixel(20, 20, Rgb([125, 125, 125]));
+    fn go_to_hole(&mut self, hole: usize) {
+        self.hole = hole.min(self.course.holes.len() - 1);
+        self.next_hole_at = None;
+        self.history.clear();
+        self.drag = None;
+        self.selected = None;
+        self.editing = None;
+        self.reset();
+    }
 
-        
+    /// Starts the course again from the first hole with an empty scorecard.
+    fn new_round(&mut self) {
+        for player in &mut self.players {
+            player.new_round(self.course.holes.len());
+        }
+        self.go_to_hole(0);
+    }
 
-        for i in &self.objects {
-            match i {
-                GameObject::Height { a, b, height } => {
-                    for i in a.x.min(b.x)..=b.x.max(a.x) {
-                        for j in a.y.min(b.y)..=b.y.max(a.y) {
-                            let og = image.get_pixel(i as u32, j as u32).0[0];
-                            
-                            image.put_pixel(i as u32, j as u32, Rgb([*height as u8+og.clamp(0, 255) as u8, 0, 0]));
-                        }
-                    }
-                }
-                GameObject::Hole(pos) => {
-                    image.put_pixel(pos.x as u32, pos.y as u32, Rgb([125-100, 125, 125]));
-                }
-                _ => {}
+    /// Records everyone's score once the last ball is in and queues up the next hole.
+    fn finish_hole(&mut self, now: f64) {
+        for player in &mut self.players {
+            player.resize_round(self.course.holes.len());
+            player.scores[self.hole] = Some(player.strokes);
+            player.hints[self.hole] = player.hints_taken;
+        }
+        // the ghost is whoever did best, if they beat the last best on this map
+        let best = self
+            .players
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, p)| p.strokes);
+        // without a map code there is nothing to play the ghost back on
+        if let Some((i, player)) = best.filter(|_| !self.recording.code.is_empty()) {
+            let beaten = self
+                .personal_bests
+                .get(&self.recording.code)
+                .map_or(true, |b| player.strokes < b.strokes);
+            if beaten {
+                let best = PersonalBest {
+                    strokes: player.strokes,
+                    replay: self.recording.for_player(i),
+                };
+                self.personal_bests
+                    .insert(self.recording.code.clone(), best);
             }
         }
+        if self.hole + 1 < self.course.holes.len() {
+            self.next_hole_at = Some(now + NEXT_HOLE_DELAY);
+        }
+    }
+
+    /// whether every ball is in the cup
+    fn hole_finished(&self) -> bool {
+        self.players.iter().all(|p| p.ball.sunk)
+    }
 
-        
-        
-        image = DynamicImage::ImageRgb8(image).resize_exact(200, 200, FilterType::Triangle).to_rgb8();
+    fn add_player(&mut self) {
+        let name = format!("Player {}", self.players.len() + 1);
+        self.players
+            .push(Player::new(name, self.course.holes.len()));
+        self.reset();
+    }
 
+    fn remove_player(&mut self, index: usize) {
+        if self.players.len() > 1 && index < self.players.len() {
+            self.players.remove(index);
+            self.reset();
+        }
+    }
 
-        
-        for x in 0..200{
-            for y in 0..200 {
-                let top = image.get_pixel(x, (y as i32-1).max(0) as u32);
-                let bottom = image.get_pixel(x, (y+1).min(199));
-                let left = image.get_pixel((x as i32-1).max(0) as u32, y);
-                let right = image.get_pixel((x+1).min(199), y);
-                let center = image.get_pixel(x, y);
+    /// Makes an edit to the current hole that can be undone.
+    fn edit_map(&mut self, command: EditCommand) {
+        self.finish_edit();
+        self.selected = None;
+        let map = &mut self.course.holes[self.hole];
+        self.history.apply(map, command);
+        self.reset();
+    }
 
-                let green = (top[0] as i32-bottom[0] as i32 + 125).clamp(0, 256) as u8;
-                let blue = (left[0] as i32-right[0] as i32 + 125).clamp(0, 256) as u8;
+    /// Picks up the object at a cell with the select tool, by one of its ends if the drag starts on one.
+    fn start_drag(&mut self, at: Point) {
+        let map = self.map();
+        let Some(index) = map.object_at(&at) else {
+            return;
+        };
+        let before = map.objects[index];
+        let handle = match before {
+            GameObject::Wall { a, b }
+            | GameObject::Height { a, b, .. }
+            | GameObject::Surface { a, b, .. }
+            | GameObject::SlidingBlock { a, b, .. }
+            | GameObject::Teleporter { a, b, .. }
+            | GameObject::Conveyor { a, b, .. }
+                if a != b =>
+            {
+                if at == a {
+                    Handle::A
+                } else if at == b {
+                    Handle::B
+                } else {
+                    Handle::Whole
+                }
+            }
+            _ => Handle::Whole,
+        };
+        self.drag = Some(Drag {
+            index,
+            before,
+            handle,
+            grab: at,
+        });
+        self.selected = Some(index);
+    }
 
-                image.put_pixel(x, y, Rgb([center[0], green, blue]));
-                
+    /// Moves the object being dragged so the part that was picked up is over `to`.
+    fn drag_to(&mut self, to: Point) {
+        let Some(drag) = self.drag else {
+            return;
+        };
+        let after = match (drag.handle, drag.before) {
+            (Handle::A, GameObject::Wall { b, .. }) => GameObject::Wall { a: to, b },
+            (Handle::B, GameObject::Wall { a, .. }) => GameObject::Wall { a, b: to },
+            (Handle::A, GameObject::Height { b, height, .. }) => {
+                GameObject::Height { a: to, b, height }
+            }
+            (Handle::B, GameObject::Height { a, height, .. }) => {
+                GameObject::Height { a, b: to, height }
+            }
+            (Handle::A, GameObject::Surface { b, surface, .. }) => {
+                GameObject::Surface { a: to, b, surface }
+            }
+            (Handle::B, GameObject::Surface { a, surface, .. }) => {
+                GameObject::Surface { a, b: to, surface }
             }
+            (Handle::A, GameObject::SlidingBlock { b, timing, .. }) => {
+                GameObject::SlidingBlock { a: to, b, timing }
+            }
+            (Handle::B, GameObject::SlidingBlock { a, timing, .. }) => {
+                GameObject::SlidingBlock { a, b: to, timing }
+            }
+            (Handle::A, GameObject::Teleporter { b, turn, .. }) => {
+                GameObject::Teleporter { a: to, b, turn }
+            }
+            (Handle::B, GameObject::Teleporter { a, turn, .. }) => {
+                GameObject::Teleporter { a, b: to, turn }
+            }
+            (Handle::A, GameObject::Conveyor { b, direction, .. }) => GameObject::Conveyor {
+                a: to,
+                b,
+                direction,
+            },
+            (Handle::B, GameObject::Conveyor { a, direction, .. }) => GameObject::Conveyor {
+                a,
+                b: to,
+                direction,
+            },
+            (_, before) => {
+                // keep the whole object on the green
+                let points = before.get_points();
+                let (min_x, max_x) = (
+                    points.iter().map(|p| p.x).min().unwrap_or(0),
+                    points.iter().map(|p| p.x).max().unwrap_or(0),
+                );
+                let (min_y, max_y) = (
+                    points.iter().map(|p| p.y).min().unwrap_or(0),
+                    points.iter().map(|p| p.y).max().unwrap_or(0),
+                );
+                let map = self.map();
+                before.moved(
+                    (to.x - drag.grab.x).clamp(-min_x, map.width - 1 - max_x),
+                    (to.y - drag.grab.y).clamp(-min_y, map.height - 1 - max_y),
+                )
+            }
+        };
+        let map = self.map_mut();
+        if drag.index < map.objects.len() && map.objects[drag.index] != after {
+            map.objects[drag.index] = after;
+            self.reset();
         }
+    }
 
-        
+    /// Drops the object being dragged, so the move can be undone.
+    fn end_drag(&mut self) {
+        let Some(drag) = self.drag.take() else {
+            return;
+        };
+        let Some(after) = self.map().objects.get(drag.index).copied() else {
+            return;
+        };
+        if after != drag.before {
+            self.history.record(EditCommand::Modify {
+                index: drag.index,
+                before: drag.before,
+                after,
+            });
+        }
+    }
 
+    /// Keeps track of a widget in the side panel changing the hole, recording the change
+    /// as one edit once the widget is let go of.
+    fn track_edit(&mut self, response: &egui::Response, before: Before) {
+        if response.changed() {
+            if self
+                .editing
+                .as_ref()
+                .map_or(false, |(id, _)| *id != response.id)
+            {
+                self.finish_edit();
+            }
+            self.editing.get_or_insert((response.id, before));
+            self.text = self.map().to_text();
+            // anything the solver is looking for or has found was for the hole as it was
+            self.search = None;
+            self.solution = None;
+        }
+        let let_go = !response.dragged() && !response.has_focus();
+        if let_go
+            && self
+                .editing
+                .as_ref()
+                .map_or(false, |(id, _)| *id == response.id)
+        {
+            self.finish_edit();
+        }
     }
 
-    fn update_hashmap(&mut self) {
-        self.map.clear();
-        for obj in &self.objects {
-            match obj {
-                GameObject::Wall { a, b } => {
-                    for i in a.x.min(b.x)..=b.x.max(a.x) {
-                        for j in a.y.min(b.y)..=b.y.max(a.y) {
-                            self.map.insert(Point { x: i, y: j }, *obj);
+    /// Shows the loop time and phase of the selected object to change, if it moves.
+    fn selected_object_ui(&mut self, ui: &mut egui::Ui) {
+        let Some(index) = self.selected else {
+            return;
+        };
+        let Some(object) = self.map().objects.get(index).copied() else {
+            return;
+        };
+        let mut timing = match object {
+            GameObject::SlidingBlock { timing, .. } | GameObject::Windmill { timing, .. } => timing,
+            _ => return,
+        };
+        ui.horizontal(|ui| {
+            let mut seconds = timing.period as f32 / 10.0;
+            let period = ui
+                .add(
+                    egui::DragValue::new(&mut seconds)
+                        .speed(0.1)
+                        .clamp_range(0.5..=60.0)
+                        .suffix("s")
+                        .prefix("Loop: "),
+                )
+                .on_hover_text("How long the selected obstacle takes to go round once");
+            if period.changed() {
+                timing.period = (seconds * 10.0).round() as i32;
+            }
+            let phase = ui
+                .add(
+                    egui::DragValue::new(&mut timing.phase)
+                        .clamp_range(0..=99)
+                        .suffix("%")
+                        .prefix("Phase: "),
+                )
+                .on_hover_text(
+                    "How far round its loop the selected obstacle is when the hole starts",
+                );
+            for response in [period, phase] {
+                if response.changed() {
+                    self.map_mut().objects[index] = match object {
+                        GameObject::SlidingBlock { a, b, .. } => {
+                            GameObject::SlidingBlock { a, b, timing }
                         }
-                    }
+                        GameObject::Windmill { pivot, arm, .. } => {
+                            GameObject::Windmill { pivot, arm, timing }
+                        }
+                        _ => object,
+                    };
+                    self.reset();
                 }
-                _ => {
-                    if let Some(p) = obj.point() {
-                        self.map.insert(p, *obj);
-                    }
+                self.track_edit(&response, Before::Object { index, object });
+            }
+        });
+    }
+
+    /// Records the change a widget is making, if there is one.
+    fn finish_edit(&mut self) {
+        let command = match self.editing.take() {
+            Some((_, Before::Properties(before))) => EditCommand::SetProperties {
+                before,
+                after: Properties::of(self.map()),
+            },
+            Some((_, Before::Object { index, object })) => {
+                let Some(after) = self.map().objects.get(index).copied() else {
+                    return;
+                };
+                EditCommand::Modify {
+                    index,
+                    before: object,
+                    after,
                 }
             }
+            None => return,
+        };
+        if !command.is_empty() {
+            self.history.record(command);
         }
     }
-}
 
-impl Default for GameObject {
-    fn default() -> Self {
-        Self::Start(Point::default())
+    fn undo(&mut self) {
+        self.finish_edit();
+        self.selected = None;
+        self.drag = None;
+        if self.history.undo(&mut self.course.holes[self.hole]) {
+            self.reset();
+        }
     }
-}
 
-impl Default for GolfMap {
-    fn default() -> Self {
-        
-        Self {
-            objects: vec![GameObject::Start(Point::default())],
-            map: HashMap::new(),
-            heightmap: RgbImage::from_pixel(200, 200, Rgb([125, 125, 125])),
+    fn redo(&mut self) {
+        self.finish_edit();
+        self.selected = None;
+        self.drag = None;
+        if self.history.redo(&mut self.course.holes[self.hole]) {
+            self.reset();
         }
     }
-}
-
-#[derive(serde::Deserialize, serde::Serialize)]
-#[serde(default)]
-pub struct App {
-    map: GolfMap,
-    ball: GolfBall,
-    edit: EditOption,
-    reset: bool,
-    slider: i32,
-    scale: f32,
-    text: String,
-}
 
-impl Default for App {
-    fn default() -> Self {
-        Self {
-            map: GolfMap::default(),
-            ball: GolfBall::default(),
-            edit: EditOption::PlayGame,
-            reset: true,
-            slider: 0,
-            scale: 3.0,
-            text: String::new(),
+    fn reset(&mut self) {
+        let map = &mut self.course.holes[self.hole];
+        // a map with its start deleted is played from the top left, the start isn't put back
+        // here as that would be an edit the history doesn't know about
+        let start = map.start().unwrap_or_default();
+        for player in &mut self.players {
+            player.start_hole(map.cell_centre(start));
         }
+        self.current = 0;
+        self.shot_in_play = false;
+
+        map.update_hashmap();
+        self.tick = 0;
+
+        map.update_heightmap();
+        self.text = map.to_text();
+        self.text_error = None;
+        self.start_recording();
+        self.solution = None;
+        self.search = None;
+        self.hint = None;
     }
-}
 
-impl App {
-    /// Called once before the first frame.
-    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
-        // This is also where you can customize the look and feel of egui using
-        // `cc.egui_ctx.set_visuals` and `cc.egui_ctx.set_fonts`.
-        // set to continuous updates
-        // Load previous app state (if any).
-        // Note that you must enable the `persistence` feature for this to work.
+    /// Starts a new replay of the hole in `text` and brings out the ghost of the best one so far.
+    fn start_recording(&mut self) {
+        self.recording = Replay::new(self.text.clone(), self.players.len(), self.balls_collide);
+        self.ghost = self
+            .personal_bests
+            .get(&self.text)
+            .and_then(|best| ReplayViewer::new(best.replay.clone()).ok());
+    }
 
+    /// Starts looking for a good next shot for the current player.
+    fn ask_for_hint(&mut self) {
+        let player = &self.players[self.current];
+        let search = Search::new(self.map(), &player.ball, self.tick, &HINT_SEARCH);
+        self.search = Some((
+            search,
+            SearchFor::Hint {
+                player: self.current,
+            },
+        ));
+        self.hint = None;
+    }
 
-        println!("new");
-        let mut new: Self = Default::default();
-        #[cfg(target_arch = "wasm32")]
-        match web_sys::window() {
-            Some(window) => {
-                
-                println!("window: {:?}",window.location().as_string());
-            }
-            None => {
-                println!("no window");
-            }
+    /// Shows the shot the solver found, taking the hint penalty if it found one.
+    fn found_hint(&mut self, player: usize, hint: Option<PlannedShot>) {
+        if let (Some(_), Some(player)) = (hint, self.players.get_mut(player)) {
+            player.hints_taken += 1;
+            player.strokes += self.hint_penalty;
         }
+        self.hint = Some(hint);
+    }
 
-        if let Some(storage) = cc.storage {
-            new = eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default();
-        }
-        new.reset();
-        new
+    /// Starts looking for the fewest strokes the hole can be done in from the start, to make that par.
+    fn work_out_par(&mut self) {
+        let map = self.map();
+        let mut ball = GolfBall::default();
+        ball.place(map.cell_centre(map.start().unwrap_or_default()));
+        let search = Search::new(map, &ball, 0, &SolverSettings::default());
+        self.search = Some((search, SearchFor::Par));
+        self.solution = None;
     }
 
-    fn reset(&mut self) {
-        let mut has_start = false;
-        for i in &self.map.objects {
-            match i {
-                GameObject::Start(p) => {
-                    has_start = true;
-                    self.ball.pos = Pos::new(p.x as f32 * 20.0 + 10.0, p.y as f32 * 20.0 + 10.0);
-                }
-                _ => {}
+    /// Carries on with the solver's search, dealing with what it found once it is done.
+    fn run_search(&mut self, ctx: &egui::Context) {
+        let Some((search, _)) = &mut self.search else {
+            return;
+        };
+        let Some(solution) = search.run(&self.course.holes[self.hole], SEARCH_SHOTS_PER_FRAME) else {
+            ctx.request_repaint();
+            return;
+        };
+        match self.search.take() {
+            Some((_, SearchFor::Par)) => self.found_par(solution),
+            Some((search, SearchFor::Hint { player })) => {
+                self.found_hint(player, search.first_shot())
             }
+            None => {}
         }
-        if !has_start {
-            self.map.objects.push(GameObject::Start(Point::default()));
-            self.ball.pos = Pos::new(10.0, 10.0);
+    }
+
+    /// Makes the fewest strokes the solver found par.
+    fn found_par(&mut self, solution: Option<Solution>) {
+        if let Some(solution) = &solution {
+            self.finish_edit();
+            let before = Properties::of(self.map());
+            let after = Properties {
+                par: solution.strokes.clamp(1, 20),
+                ..before.clone()
+            };
+            // not through `edit_map`, resetting would lose the solution before it is shown
+            self.history.apply(
+                &mut self.course.holes[self.hole],
+                EditCommand::SetProperties { before, after },
+            );
+            self.text = self.map().to_text();
         }
+        self.solution = Some(solution);
+    }
 
-        match self.edit {
-            EditOption::EditMap(GameObject::Wall { a: _, b: _ }) => {
-                self.map.objects.push(GameObject::Wall {
-                    a: Point { x: -1, y: -1 },
-                    b: Point { x: -1, y: -1 },
-                });
+    /// Opens a replay in the replay window and starts it playing.
+    fn watch(&mut self, replay: Replay) {
+        match ReplayViewer::new(replay) {
+            Ok(viewer) => {
+                self.viewer = Some(viewer);
+                self.replay_error = None;
+                self.show_replay = true;
             }
-            _ => {}
+            Err(error) => self.replay_error = Some(error),
         }
-
-        self.map.update_hashmap();
-        self.ball.vel = Pos::default();
-
-        self.map.update_heightmap();
-        self.text = self.map.to_text();
     }
 }
 
@@ -506,49 +972,288 @@ impl eframe::App for App {
     /// Called by the frame work to save state before shutdown.
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
         eframe::set_value(storage, eframe::APP_KEY, self);
+        self.library.save(storage);
     }
 
     /// Called each time the UI needs repainting, which may be many times per second.
     /// Put your widgets into a `SidePanel`, `TopPanel`, `CentralPanel`, `Window` or `Area`.
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-
+        self.run_search(ctx);
 
         egui::TopBottomPanel::bottom("bottom panel").show(ctx, |ui| {
-            ui.text_edit_singleline(&mut self.text);
+            let response = ui
+                .text_edit_singleline(&mut self.text)
+                .on_hover_text("Paste or type a map code and press enter to load it");
+            // only once the code is all there, not a character at a time
+            if response.lost_focus() && self.text != self.map().to_text() {
+                self.load_code();
+            }
+            if let Some(error) = &self.text_error {
+                ui.colored_label(ui.visuals().error_fg_color, error.to_string());
+            }
         });
 
         egui::SidePanel::left("side_panel").show(ctx, |ui| {
+            ui.heading(format!("Hole {}: {}", self.hole + 1, self.map().name));
             if ui.button("Reset").clicked() {
                 self.reset = true;
             };
-            // ui elements to edit the game
-            ui.separator();
-            if ui
-                .selectable_label(self.edit == EditOption::PlayGame, "Play Game")
-                .clicked()
-            {
-                self.edit = EditOption::PlayGame;
-            };
-            ui.separator();
-            if ui.button("clear map").clicked() {
-                self.map.objects.clear();
-                self.map.objects.push(GameObject::Start(Point {x:1, y:1}));
-                self.map.objects.push(GameObject::Hole(Point {x:18, y:18}));
-                self.map.update_hashmap();
-                self.reset();
-            }
-            ui.separator();
             ui.horizontal(|ui| {
-                if ui.small_button("-").clicked() {
-                    self.scale *= 0.75;
-                }
-                ui.add(egui::DragValue::new(&mut self.scale).speed(0.001).clamp_range(0.0..=4.0).prefix("Scale: "));
-                if ui.small_button("+").clicked() {
-                    self.scale *= 1.25;
+                let ball = &self.players[self.current].ball;
+                let can_hint = self.edit == EditOption::PlayGame
+                    && !self.shot_in_play
+                    && ball.at_rest()
+                    && !ball.sunk
+                    && self.search.is_none();
+                let cost = match self.hint_penalty {
+                    0 => "it is counted on the scorecard".to_owned(),
+                    1 => "it costs a stroke".to_owned(),
+                    n => format!("it costs {n} strokes"),
+                };
+                if let Some((search, SearchFor::Hint { .. })) = &self.search {
+                    ui.add(egui::ProgressBar::new(search.progress()).desired_width(60.0));
+                    if ui.button("Cancel").clicked() {
+                        self.search = None;
+                    }
+                } else if ui
+                    .add_enabled(can_hint, egui::Button::new("Hint"))
+                    .on_hover_text(format!("Shows a good shot from here, {cost}"))
+                    .clicked()
+                {
+                    self.ask_for_hint();
                 }
+                ui.label("Penalty:");
+                ui.add(egui::DragValue::new(&mut self.hint_penalty).clamp_range(0..=3));
             });
-            if ui
-                .selectable_label(self.edit == EditOption::Delete, "Delete")
+            match self.hint {
+                Some(None) => {
+                    ui.colored_label(ui.visuals().error_fg_color, "No good shot found from here");
+                }
+                Some(Some(_)) if self.map().has_moving_obstacles() => {
+                    ui.label("The obstacles will have moved on since the hint was worked out, so it may not go in");
+                }
+                _ => {}
+            }
+            if let Some(best) = self.personal_bests.get(&self.text) {
+                ui.horizontal(|ui| {
+                    ui.label(format!("Best: {}", best.strokes));
+                    ui.checkbox(&mut self.show_ghost, "Show ghost")
+                        .on_hover_text("A see-through ball plays the best attempt alongside this one");
+                });
+            }
+            if self.players.len() == 1 {
+                ui.label(format!("Strokes: {}", self.players[0].strokes));
+            } else {
+                for (i, player) in self.players.iter().enumerate() {
+                    let text = egui::RichText::new(format!("{}: {}", player.name, player.strokes))
+                        .color(render::BALL_COLOURS[i % render::BALL_COLOURS.len()]);
+                    ui.label(if i == self.current && !player.ball.sunk { text.strong().underline() } else { text });
+                }
+            }
+            ui.collapsing("Players", |ui| {
+                let mut remove = None;
+                for (i, player) in self.players.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.colored_label(render::BALL_COLOURS[i % render::BALL_COLOURS.len()], "⏺");
+                        ui.add(egui::TextEdit::singleline(&mut player.name).desired_width(100.0));
+                        if ui.small_button("✖").on_hover_text("Remove this player").clicked() {
+                            remove = Some(i);
+                        }
+                    });
+                }
+                if let Some(i) = remove {
+                    self.remove_player(i);
+                }
+                if ui.add_enabled(self.players.len() < MAX_PLAYERS, egui::Button::new("Add player")).clicked() {
+                    self.add_player();
+                }
+                egui::ComboBox::from_label("Next shot")
+                    .selected_text(self.turn_order.name())
+                    .show_ui(ui, |ui| {
+                        for order in TurnOrder::iter() {
+                            ui.selectable_value(&mut self.turn_order, order, order.name());
+                        }
+                    });
+                if ui
+                    .checkbox(&mut self.balls_collide, "Balls knock each other")
+                    .on_hover_text("Otherwise they roll straight through each other, changing this starts the hole again")
+                    .changed()
+                {
+                    self.reset = true;
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Par:");
+                let before = Properties::of(self.map());
+                let response = ui.add(egui::DragValue::new(&mut self.map_mut().par).clamp_range(1..=20));
+                self.track_edit(&response, Before::Properties(before));
+                if let Some((search, SearchFor::Par)) = &self.search {
+                    ui.add(egui::ProgressBar::new(search.progress()).desired_width(80.0));
+                    if ui.button("Cancel").clicked() {
+                        self.search = None;
+                    }
+                } else if ui
+                    .add_enabled(self.search.is_none(), egui::Button::new("Work out"))
+                    .on_hover_text("Tries shots until it finds the fewest strokes the hole can be done in, and sets par to that")
+                    .clicked()
+                {
+                    self.work_out_par();
+                }
+            });
+            let mut watch_solution = None;
+            match &self.solution {
+                Some(Some(solution)) => {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("Can be done in {}", solution.strokes));
+                        if ui.button("Watch").clicked() {
+                            watch_solution = Some(Replay::from_solution(self.map().to_text(), solution));
+                        }
+                    });
+                }
+                Some(None) => {
+                    let strokes = SolverSettings::default().max_strokes;
+                    ui.colored_label(ui.visuals().error_fg_color, format!("No way into the hole found in {strokes} strokes"));
+                }
+                None => {}
+            }
+            if let Some(replay) = watch_solution {
+                self.watch(replay);
+            }
+            let before = Properties::of(self.map());
+            let response = ui.add(egui::Slider::new(&mut self.map_mut().restitution, 0.0..=1.0).text("Wall bounce"));
+            self.track_edit(&response, Before::Properties(before));
+            ui.horizontal(|ui| {
+                let (mut width, mut height) = (self.map().width, self.map().height);
+                ui.label("Size:");
+                let changed = ui.add(egui::DragValue::new(&mut width).clamp_range(GRID_SIZES)).changed()
+                    | ui.add(egui::DragValue::new(&mut height).clamp_range(GRID_SIZES).prefix("x ")).changed();
+                if changed {
+                    self.edit_map(EditCommand::resize(self.map(), width, height));
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Cell size:");
+                let before = Properties::of(self.map());
+                let mut cell_size = before.cell_size;
+                let drag = egui::DragValue::new(&mut cell_size).clamp_range(CELL_SIZES).fixed_decimals(0).suffix("px");
+                let response = ui.add(drag);
+                if response.changed() {
+                    // map codes only keep whole pixels
+                    self.map_mut().cell_size = cell_size.round();
+                    self.reset();
+                }
+                self.track_edit(&response, Before::Properties(before));
+            });
+            if ui
+                .button("Copy share link")
+                .on_hover_text("Copies a link to this hole that anyone can open in their browser")
+                .clicked()
+            {
+                let link = share_link(&self.map().to_text());
+                ui.output_mut(|o| o.copied_text = link);
+            }
+            if ui.button("Scorecard").clicked() {
+                self.show_scorecard = !self.show_scorecard;
+            }
+            if ui.button("Map library").clicked() {
+                self.show_library = !self.show_library;
+            }
+            if ui.button("Replay").clicked() {
+                self.show_replay = !self.show_replay;
+            }
+            ui.separator();
+            ui.collapsing("Course", |ui| {
+                ui.text_edit_singleline(&mut self.course.name);
+                let mut go_to = None;
+                for (i, hole) in self.course.holes.iter().enumerate() {
+                    if ui
+                        .selectable_label(i == self.hole, format!("{}. {}", i + 1, hole.name))
+                        .clicked()
+                    {
+                        go_to = Some(i);
+                    }
+                }
+                ui.horizontal(|ui| {
+                    ui.label("Name:");
+                    let before = Properties::of(self.map());
+                    let response = ui.text_edit_singleline(&mut self.map_mut().name);
+                    self.track_edit(&response, Before::Properties(before));
+                });
+                ui.horizontal(|ui| {
+                    if ui.button("Add hole").clicked() {
+                        let mut map = GolfMap::default();
+                        map.name = format!("Hole {}", self.course.holes.len() + 1);
+                        self.course.holes.push(map);
+                        for player in &mut self.players {
+                            player.resize_round(self.course.holes.len());
+                        }
+                        go_to = Some(self.course.holes.len() - 1);
+                    }
+                    if ui
+                        .add_enabled(self.course.holes.len() > 1, egui::Button::new("Remove hole"))
+                        .clicked()
+                    {
+                        self.course.holes.remove(self.hole);
+                        for player in &mut self.players {
+                            player.remove_hole(self.hole);
+                        }
+                        go_to = Some(self.hole.saturating_sub(1));
+                    }
+                });
+                if let Some(i) = go_to {
+                    self.go_to_hole(i);
+                }
+            });
+            // ui elements to edit the game
+            ui.separator();
+            if ui
+                .selectable_label(self.edit == EditOption::PlayGame, "Play Game")
+                .clicked()
+            {
+                self.edit = EditOption::PlayGame;
+            };
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(self.history.can_undo(), egui::Button::new("Undo"))
+                    .on_hover_text("Ctrl+Z")
+                    .clicked()
+                {
+                    self.undo();
+                }
+                if ui
+                    .add_enabled(self.history.can_redo(), egui::Button::new("Redo"))
+                    .on_hover_text("Ctrl+Shift+Z")
+                    .clicked()
+                {
+                    self.redo();
+                }
+            });
+            if ui.button("clear map").clicked() {
+                self.edit_map(EditCommand::clear(self.map()));
+            }
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.small_button("-").clicked() {
+                    self.scale *= 0.75;
+                }
+                ui.add(egui::DragValue::new(&mut self.scale).speed(0.001).clamp_range(0.0..=4.0).prefix("Scale: "));
+                if ui.small_button("+").clicked() {
+                    self.scale *= 1.25;
+                }
+            });
+            if ui
+                .selectable_label(self.edit == EditOption::Select, "Select")
+                .on_hover_text("Drag an object to move it, or drag one of its ends to resize it. Click a moving obstacle to change its timing")
+                .clicked()
+            {
+                self.edit = EditOption::Select;
+            };
+            if self.edit == EditOption::Select {
+                self.selected_object_ui(ui);
+            }
+            if ui
+                .selectable_label(self.edit == EditOption::Delete, "Delete")
                 .clicked()
             {
                 self.edit = EditOption::Delete;
@@ -593,7 +1298,6 @@ impl eframe::App for App {
                 });
             };
             ui.separator();
-            ui.small("folowing options are not implemented yet and will be avalible in a future version");
             if ui
                 .selectable_label(
                     matches!(self.edit
@@ -615,27 +1319,571 @@ impl eframe::App for App {
 
             ui.add(egui::Slider::new(&mut self.slider, -40..=40).integer().drag_value_speed(0.1).prefix("Height: "));
 
-            
-            
+            ui.separator();
+            if ui
+                .selectable_label(matches!(self.edit, EditOption::EditMap(GameObject::Surface { .. })), "Add Surface")
+                .clicked()
+            {
+                self.edit = EditOption::EditMap(GameObject::Surface {
+                    a: Point { x: -1, y: -1 },
+                    b: Point { x: -1, y: -1 },
+                    surface: self.surface,
+                });
+            };
+            egui::ComboBox::from_label("Surface")
+                .selected_text(self.surface.name())
+                .show_ui(ui, |ui| {
+                    for surface in Surface::iter() {
+                        ui.selectable_value(&mut self.surface, surface, surface.name());
+                    }
+                });
+
+            ui.separator();
+            if ui
+                .selectable_label(matches!(self.edit, EditOption::EditMap(GameObject::SlidingBlock { .. })), "Add Sliding Block")
+                .on_hover_text("Click where the block starts, then where it slides to")
+                .clicked()
+            {
+                self.edit = EditOption::EditMap(GameObject::SlidingBlock {
+                    a: Point { x: -1, y: -1 },
+                    b: Point { x: -1, y: -1 },
+                    timing: self.timing,
+                });
+            };
+            if ui
+                .selectable_label(matches!(self.edit, EditOption::EditMap(GameObject::Windmill { .. })), "Add Windmill")
+                .clicked()
+            {
+                self.edit = EditOption::EditMap(GameObject::Windmill {
+                    pivot: Point::default(),
+                    arm: self.arm,
+                    timing: self.timing,
+                });
+            };
+            ui.horizontal(|ui| {
+                let mut seconds = self.timing.period as f32 / 10.0;
+                if ui
+                    .add(egui::DragValue::new(&mut seconds).speed(0.1).clamp_range(0.5..=60.0).suffix("s").prefix("Loop: "))
+                    .on_hover_text("How long a moving obstacle takes to go round once")
+                    .changed()
+                {
+                    self.timing.period = (seconds * 10.0).round() as i32;
+                }
+                ui.add(egui::DragValue::new(&mut self.timing.phase).clamp_range(0..=99).suffix("%").prefix("Phase: "))
+                    .on_hover_text("How far round its loop the obstacle is when the hole starts");
+            });
+            ui.add(egui::DragValue::new(&mut self.arm).clamp_range(ARM_LENGTHS).prefix("Windmill reach: "));
+
+            ui.separator();
+            if ui
+                .selectable_label(matches!(self.edit, EditOption::EditMap(GameObject::Teleporter { .. })), "Add Teleporter")
+                .on_hover_text("Click where one pad goes, then where the other goes")
+                .clicked()
+            {
+                self.edit = EditOption::EditMap(GameObject::Teleporter {
+                    a: Point { x: -1, y: -1 },
+                    b: Point { x: -1, y: -1 },
+                    turn: self.turn,
+                });
+            };
+            egui::ComboBox::from_label("Exit")
+                .selected_text(turn_name(self.turn))
+                .show_ui(ui, |ui| {
+                    for turn in 0..4 {
+                        ui.selectable_value(&mut self.turn, turn, turn_name(turn));
+                    }
+                });
+
+            ui.separator();
+            if ui
+                .selectable_label(matches!(self.edit, EditOption::EditMap(GameObject::Booster { .. })), "Add Booster")
+                .on_hover_text("Kicks the ball along its arrow as it rolls on")
+                .clicked()
+            {
+                self.edit = EditOption::EditMap(GameObject::Booster { pos: Point::default(), direction: self.direction });
+            };
+            if ui
+                .selectable_label(matches!(self.edit, EditOption::EditMap(GameObject::Conveyor { .. })), "Add Conveyor")
+                .on_hover_text("Click one corner of the belt, then the other")
+                .clicked()
+            {
+                self.edit = EditOption::EditMap(GameObject::Conveyor {
+                    a: Point { x: -1, y: -1 },
+                    b: Point { x: -1, y: -1 },
+                    direction: self.direction,
+                });
+            };
+            // the arrows laid out the way they point, round an empty middle
+            let compass = [
+                [Some(Direction::UpLeft), Some(Direction::Up), Some(Direction::UpRight)],
+                [Some(Direction::Left), None, Some(Direction::Right)],
+                [Some(Direction::DownLeft), Some(Direction::Down), Some(Direction::DownRight)],
+            ];
+            ui.separator();
+            if ui
+                .selectable_label(matches!(self.edit, EditOption::EditMap(GameObject::Rail { .. })), "Add Rail")
+                .on_hover_text("A thin wall at any angle, click where it starts then where it ends")
+                .clicked()
+            {
+                self.edit = EditOption::EditMap(GameObject::Rail {
+                    a: Point { x: -1, y: -1 },
+                    b: Point { x: -1, y: -1 },
+                    thickness: self.rail_thickness,
+                });
+            };
+            ui.horizontal(|ui| {
+                let mut thickness = self.rail_thickness as f32 / RAIL_STEPS as f32;
+                if ui
+                    .add(
+                        egui::DragValue::new(&mut thickness)
+                            .speed(0.05)
+                            .clamp_range(0.1..=1.0)
+                            .fixed_decimals(1)
+                            .prefix("Thickness: ")
+                            .suffix(" cells"),
+                    )
+                    .changed()
+                {
+                    self.rail_thickness = ((thickness * RAIL_STEPS as f32).round() as i32).clamp(*RAIL_THICKNESSES.start(), *RAIL_THICKNESSES.end());
+                }
+            });
+            if ui
+                .selectable_label(matches!(self.edit, EditOption::EditMap(GameObject::Bumper { .. })), "Add Bumper")
+                .clicked()
+            {
+                self.edit = EditOption::EditMap(GameObject::Bumper {
+                    centre: Point::default(),
+                    radius: self.bumper_radius,
+                    bounce: self.bumper_bounce,
+                });
+            };
+            ui.horizontal(|ui| {
+                let mut radius = self.bumper_radius as f32 / RAIL_STEPS as f32;
+                if ui
+                    .add(egui::DragValue::new(&mut radius).speed(0.05).clamp_range(0.2..=3.0).fixed_decimals(1).prefix("Size: ").suffix(" cells"))
+                    .changed()
+                {
+                    self.bumper_radius = ((radius * RAIL_STEPS as f32).round() as i32).clamp(*BUMPER_RADII.start(), *BUMPER_RADII.end());
+                }
+                ui.add(egui::DragValue::new(&mut self.bumper_bounce).clamp_range(BUMPER_BOUNCES).prefix("Bounce: ").suffix("%"))
+                    .on_hover_text("Over 100% kicks the ball away faster than it came in");
+            });
+            if ui
+                .selectable_label(matches!(self.edit, EditOption::EditMap(GameObject::Arc { .. })), "Add Curved Wall")
+                .on_hover_text("Click the middle of the curve, then where it starts going round from")
+                .clicked()
+            {
+                self.edit = EditOption::EditMap(GameObject::Arc {
+                    centre: Point { x: -1, y: -1 },
+                    radius: 0,
+                    from: 0,
+                    sweep: self.arc_sweep,
+                    thickness: self.rail_thickness,
+                });
+            };
+            ui.add(egui::DragValue::new(&mut self.arc_sweep).clamp_range(ARC_SWEEPS).prefix("Curve: ").suffix("°"));
+            egui::ComboBox::from_label("Snap to")
+                .selected_text(self.snap.name())
+                .show_ui(ui, |ui| {
+                    for snap in Snap::iter() {
+                        ui.selectable_value(&mut self.snap, snap, snap.name());
+                    }
+                });
+            // the tool picks up any change to its settings straight away
+            match &mut self.edit {
+                EditOption::EditMap(GameObject::Rail { thickness, .. }) => *thickness = self.rail_thickness,
+                EditOption::EditMap(GameObject::Bumper { radius, bounce, .. }) => {
+                    *radius = self.bumper_radius;
+                    *bounce = self.bumper_bounce;
+                }
+                EditOption::EditMap(GameObject::Arc { sweep, thickness, .. }) => {
+                    *sweep = self.arc_sweep;
+                    *thickness = self.rail_thickness;
+                }
+                _ => {}
+            }
+            ui.separator();
+            egui::Grid::new("direction_picker").spacing([2.0, 2.0]).show(ui, |ui| {
+                for row in compass {
+                    for direction in row {
+                        match direction {
+                            Some(direction) => {
+                                ui.selectable_value(&mut self.direction, direction, direction.arrow());
+                            }
+                            None => {
+                                ui.label("");
+                            }
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+
+
+
 
         });
 
+        let now = ctx.input(|i| i.time);
+
+        if self.hole_finished() {
+            let mut next_hole = false;
+            let mut watch = false;
+            egui::Window::new("Hole complete")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    let par = self.map().par;
+                    if let [player] = &self.players[..] {
+                        ui.heading(score_name(player.strokes, par));
+                        ui.label(format!("Strokes: {}", player.strokes));
+                    } else {
+                        egui::Grid::new("hole_results").show(ui, |ui| {
+                            for player in &self.players {
+                                ui.strong(&player.name);
+                                ui.label(format!("{}", player.strokes));
+                                ui.label(score_name(player.strokes, par));
+                                ui.end_row();
+                            }
+                        });
+                    }
+                    ui.label(format!("Par: {par}"));
+                    if ui.button("Watch replay").clicked() {
+                        watch = true;
+                    }
+                    ui.separator();
+                    if self.hole + 1 < self.course.holes.len() {
+                        if let Some(at) = self.next_hole_at {
+                            ui.label(format!("Next hole in {:.0}s", (at - now).max(0.0).ceil()));
+                        }
+                        if ui.button("Next hole").clicked() {
+                            next_hole = true;
+                        }
+                    } else {
+                        ui.label("Round complete");
+                        for player in &self.players {
+                            let total: u32 = (0..self.course.holes.len())
+                                .filter_map(|i| player.score(i))
+                                .sum();
+                            ui.label(format!(
+                                "{}: {} strokes ({})",
+                                player.name,
+                                total,
+                                to_par_text(total as i32 - self.course.total_par() as i32)
+                            ));
+                        }
+                        if ui.button("Play again").clicked() {
+                            self.new_round();
+                        }
+                    }
+                });
+            if let Some(at) = self.next_hole_at {
+                if now >= at {
+                    next_hole = true;
+                } else {
+                    ctx.request_repaint_after(Duration::from_secs_f64(at - now));
+                }
+            }
+            if watch {
+                self.watch(self.recording.clone());
+            }
+            if next_hole {
+                self.go_to_hole(self.hole + 1);
+            }
+        }
+
+        let mut show_scorecard = self.show_scorecard;
+        egui::Window::new("Scorecard")
+            .open(&mut show_scorecard)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(&self.course.name);
+                egui::Grid::new("scorecard").striped(true).show(ui, |ui| {
+                    ui.strong("Hole");
+                    ui.strong("Name");
+                    ui.strong("Par");
+                    // a pair of columns for each player, headed with their name when there is more than one
+                    for player in &self.players {
+                        ui.strong(if self.players.len() == 1 {
+                            "Strokes"
+                        } else {
+                            &player.name
+                        });
+                        ui.strong("To par");
+                    }
+                    ui.end_row();
+
+                    let mut totals = vec![(0, 0); self.players.len()];
+                    for (i, hole) in self.course.holes.iter().enumerate() {
+                        ui.label(format!("{}", i + 1));
+                        ui.label(&hole.name);
+                        ui.label(format!("{}", hole.par));
+                        for (player, (total, to_par)) in self.players.iter().zip(&mut totals) {
+                            match player.score(i) {
+                                Some(strokes) => {
+                                    *total += strokes;
+                                    *to_par += strokes as i32 - hole.par as i32;
+                                    ui.label(format!("{strokes}"));
+                                    ui.label(to_par_text(*to_par));
+                                }
+                                None => {
+                                    ui.label("-");
+                                    ui.label("");
+                                }
+                            }
+                        }
+                        ui.end_row();
+                    }
+
+                    ui.strong("Total");
+                    ui.label("");
+                    ui.strong(format!("{}", self.course.total_par()));
+                    for (total, to_par) in totals {
+                        ui.strong(format!("{total}"));
+                        ui.strong(to_par_text(to_par));
+                    }
+                    ui.end_row();
+
+                    if self.players.iter().any(|p| p.hints.iter().any(|h| *h > 0)) {
+                        ui.label("Hints");
+                        ui.label("");
+                        ui.label("");
+                        for player in &self.players {
+                            ui.label(format!("{}", player.hints.iter().sum::<u32>()));
+                            ui.label("");
+                        }
+                        ui.end_row();
+                    }
+                });
+                if ui.button("New round").clicked() {
+                    self.new_round();
+                }
+            });
+        self.show_scorecard = show_scorecard;
+
+        let mut show_library = self.show_library;
+        egui::Window::new("Map library")
+            .open(&mut show_library)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Author:");
+                    ui.text_edit_singleline(&mut self.author);
+                });
+                if ui.button("Save current hole").clicked() {
+                    self.library
+                        .add(&self.course.holes[self.hole], &self.author);
+                }
+                ui.separator();
+                if self.library.maps.is_empty() {
+                    ui.label("Nothing saved yet");
+                }
+                let now = library::now();
+                let mut open = None;
+                let mut overwrite = None;
+                let mut duplicate = None;
+                let mut remove = None;
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    egui::Grid::new("library").striped(true).show(ui, |ui| {
+                        for (i, saved) in self.library.maps.iter_mut().enumerate() {
+                            ui.add(
+                                egui::TextEdit::singleline(&mut saved.title).desired_width(120.0),
+                            );
+                            ui.label(if saved.author.is_empty() {
+                                "-"
+                            } else {
+                                &saved.author
+                            });
+                            ui.label(library::age_text(saved.saved_at, now));
+                            if ui
+                                .button("Open")
+                                .on_hover_text("Replace the current hole with this map")
+                                .clicked()
+                            {
+                                open = Some(i);
+                            }
+                            if ui
+                                .button("Save over")
+                                .on_hover_text("Save the current hole in place of this map")
+                                .clicked()
+                            {
+                                overwrite = Some(i);
+                            }
+                            if ui.button("Duplicate").clicked() {
+                                duplicate = Some(i);
+                            }
+                            if ui.button("Delete").clicked() {
+                                remove = Some(i);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                });
+                if let Some(i) = open {
+                    self.open_saved_map(i);
+                }
+                if let Some(i) = overwrite {
+                    self.library.overwrite(i, &self.course.holes[self.hole]);
+                }
+                if let Some(i) = duplicate {
+                    self.library.duplicate(i);
+                }
+                if let Some(i) = remove {
+                    self.library.remove(i);
+                }
+            });
+        self.show_library = show_library;
+
+        let mut show_replay = self.show_replay;
+        egui::Window::new("Replay")
+            .open(&mut show_replay)
+            .show(ctx, |ui| {
+                let mut watch = None;
+                ui.horizontal(|ui| {
+                    if ui.button("Watch this hole").clicked() {
+                        watch = Some(self.recording.clone());
+                    }
+                    if ui
+                        .button("Copy replay code")
+                        .on_hover_text(
+                            "Copies the map code with every shot taken on this hole after it",
+                        )
+                        .clicked()
+                    {
+                        let code = self.recording.to_text();
+                        ui.output_mut(|o| o.copied_text = code);
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.replay_text)
+                            .hint_text("Paste a replay code"),
+                    );
+                    if ui.button("Watch").clicked() {
+                        match Replay::from_text(&self.replay_text) {
+                            Ok(replay) => watch = Some(replay),
+                            Err(error) => self.replay_error = Some(error),
+                        }
+                    }
+                });
+                if let Some(error) = &self.replay_error {
+                    ui.colored_label(ui.visuals().error_fg_color, error.to_string());
+                }
+                if let Some(replay) = watch {
+                    self.watch(replay);
+                }
+
+                let Some(viewer) = &mut self.viewer else {
+                    return;
+                };
+                viewer.advance(ctx.input(|i| i.unstable_dt).min(MAX_FRAME_TIME));
+                if viewer.playing {
+                    ctx.request_repaint();
+                }
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("⏮").on_hover_text("Back to the start").clicked() {
+                        viewer.seek(0);
+                    }
+                    if viewer.playing {
+                        if ui.button("⏸").clicked() {
+                            viewer.playing = false;
+                        }
+                    } else if ui.button("▶").clicked() {
+                        viewer.play();
+                    }
+                    egui::ComboBox::from_id_source("replay_speed")
+                        .width(60.0)
+                        .selected_text(format!("{}x", viewer.speed))
+                        .show_ui(ui, |ui| {
+                            for speed in replay::SPEEDS {
+                                ui.selectable_value(&mut viewer.speed, speed, format!("{speed}x"));
+                            }
+                        });
+                    let mut at = viewer.tick;
+                    let timeline = egui::Slider::new(&mut at, 0..=viewer.length)
+                        .show_value(false)
+                        .custom_formatter(|tick, _| format!("{:.1}s", tick as f32 * PHYSICS_DT));
+                    if ui.add(timeline).changed() {
+                        viewer.seek(at);
+                    }
+                    ui.label(format!(
+                        "{:.1}s / {:.1}s",
+                        viewer.tick as f32 * PHYSICS_DT,
+                        viewer.length as f32 * PHYSICS_DT
+                    ));
+                });
+                ui.label(format!(
+                    "Shot {} of {}",
+                    viewer.shots_taken(),
+                    viewer.replay.shots.len()
+                ));
+                egui::ScrollArea::both().max_height(600.0).show(ui, |ui| {
+                    let size = viewer.map.pixel_size();
+                    let (response, painter) =
+                        ui.allocate_painter(egui::vec2(size.x, size.y), egui::Sense::hover());
+                    let origin = response.rect.left_top();
+                    render::paint_green(&painter, origin, &viewer.map, viewer.tick, false, None);
+                    for (i, player) in viewer.players.iter().enumerate() {
+                        render::paint_ball(
+                            &painter,
+                            origin,
+                            &player.ball,
+                            render::BALL_COLOURS[i % render::BALL_COLOURS.len()],
+                        );
+                    }
+                });
+            });
+        self.show_replay = show_replay;
+
         if self.reset {
             self.reset();
             self.reset = false;
         }
 
+        // leave ctrl+z alone while a text box is being typed in, it has its own undo
+        if !ctx.wants_keyboard_input() {
+            let redo = ctx.input_mut(|i| {
+                i.consume_shortcut(&KeyboardShortcut::new(
+                    Modifiers::COMMAND | Modifiers::SHIFT,
+                    Key::Z,
+                ))
+            });
+            let undo = ctx.input_mut(|i| {
+                i.consume_shortcut(&KeyboardShortcut::new(Modifiers::COMMAND, Key::Z))
+            });
+            if redo {
+                self.redo();
+            } else if undo {
+                self.undo();
+            }
+        }
+
         let Self {
-            map,
+            course,
+            hole,
             edit,
-            ball,
+            players,
+            current,
+            turn_order,
+            balls_collide,
+            shot_in_play,
+            recording,
+            show_ghost,
+            ghost,
+            hint,
+            search,
             scale,
+            accumulator,
+            tick,
+            drag,
+            selected,
+            snap,
             ..
         } = self;
-        
-        // if not web then print hello
-        
+        let map = &mut course.holes[*hole];
 
+        // if not web then print hello
 
         #[cfg(not(target_arch = "wasm32"))]
         ctx.set_pixels_per_point(*scale);
@@ -644,161 +1892,252 @@ impl eframe::App for App {
         // Pick whichever suits you.
         // Tip: a good default choice is to just keep the `CentralPanel`.
         // For inspiration and more examples, go to https://emilk.github.io/egui
-        let mut delta = 0.0;
-        ctx.input(|i| {
-            delta = i.predicted_dt;
-        });
-
-        // update movement of ball every frame
-        ball.update_pos(map, delta);
+        // run however many fixed steps fit in the time since the last frame,
+        // so a shot plays out the same whatever the frame rate is
+        *accumulator += ctx.input(|i| i.unstable_dt).min(MAX_FRAME_TIME);
+        let was_finished = players.iter().all(|p| p.ball.sunk);
+        while *accumulator >= PHYSICS_DT {
+            players::step(players, map, *tick, *balls_collide);
+            *tick = tick.wrapping_add(1);
+            *accumulator -= PHYSICS_DT;
+        }
+        // once every ball has stopped it is the next player's turn
+        if *shot_in_play && players.iter().all(|p| p.ball.at_rest()) {
+            *shot_in_play = false;
+            if let Some(next) = players::next_player(players, *current, *turn_order, map) {
+                *current = next;
+            }
+        }
+        let just_finished = !was_finished && players.iter().all(|p| p.ball.sunk);
+        if let Some(ghost) = ghost {
+            ghost.seek(*tick);
+        }
 
         let mut clicked_point = None;
-
-
+        // the place on the rail grid that was clicked, for placing rails
+        let mut clicked_spot = None;
+        // cells the select tool picked an object up from and dragged it to
+        let mut drag_from = None;
+        let mut dragged_to = None;
+        let mut drag_released = false;
 
         egui::CentralPanel::default().show(ctx, |ui| {
-            let mut clicked = false;
-            ctx.input(|o| {
-                clicked = o.events.iter().any(|e| {
-                    matches!(
-                        e,
-                        egui::Event::PointerButton {
-                            button: egui::PointerButton::Primary,
-                            pressed: true,
-                            ..
+            // the green is painted rather than built out of buttons, so it stays quick however big it is
+            egui::ScrollArea::both()
+                .drag_to_scroll(false)
+                .show(ui, |ui| {
+                    let size = map.pixel_size();
+                    let (response, painter) = ui.allocate_painter(
+                        egui::vec2(size.x, size.y),
+                        egui::Sense::click_and_drag(),
+                    );
+                    let green_rect = response.rect;
+                    let cell_under = |pos: Pos2| {
+                        let cell = map.cell_at(Pos::new(
+                            pos.x - green_rect.left(),
+                            pos.y - green_rect.top(),
+                        ));
+                        Some(cell).filter(|c| map.contains(c))
+                    };
+                    let hover = response.hover_pos().and_then(cell_under);
+                    // the nearest place a rail end can go, which can be on the far edges
+                    let spot_under = |pos: Pos2| {
+                        let step = map.cell_size / RAIL_STEPS as f32 * snap.steps() as f32;
+                        let snapped = |offset: f32, cells: i32| {
+                            ((offset / step).round() as i32 * snap.steps())
+                                .clamp(0, cells * RAIL_STEPS)
+                        };
+                        Point {
+                            x: snapped(pos.x - green_rect.left(), map.width),
+                            y: snapped(pos.y - green_rect.top(), map.height),
                         }
-                    )
-                });
-            });
+                    };
+                    if response.clicked() {
+                        clicked_point = response.interact_pointer_pos().and_then(cell_under);
+                        clicked_spot = response.interact_pointer_pos().map(spot_under);
+                    }
+                    if *edit == EditOption::Select {
+                        if response.drag_started() {
+                            drag_from = ui.input(|i| i.pointer.press_origin()).and_then(cell_under);
+                        }
+                        if response.dragged() {
+                            // dragging off the edge holds the object against it
+                            dragged_to = response.interact_pointer_pos().map(|pos| {
+                                let cell = map.cell_at(Pos::new(
+                                    pos.x - green_rect.left(),
+                                    pos.y - green_rect.top(),
+                                ));
+                                Point {
+                                    x: cell.x.clamp(0, map.width - 1),
+                                    y: cell.y.clamp(0, map.height - 1),
+                                }
+                            });
+                        }
+                        drag_released = response.drag_released();
+                    }
+                    render::paint_green(
+                        &painter,
+                        green_rect.left_top(),
+                        map,
+                        *tick,
+                        !matches!(edit, EditOption::PlayGame),
+                        hover,
+                    );
+                    if *edit == EditOption::Select {
+                        let selected = match drag {
+                            Some(drag) => map.objects.get(drag.index),
+                            None => hover
+                                .and_then(|p| map.object_at(&p))
+                                .or(*selected)
+                                .and_then(|i| map.objects.get(i)),
+                        };
+                        if let Some(object) = selected {
+                            render::paint_selection(&painter, green_rect.left_top(), map, object);
+                        }
+                    }
 
-            // spawn playing green
-            let green_rect = egui::Area::new("green_area")
-                .movable(true)
-                .show(ctx, |ui| {
-                    egui::Frame::default()
-                        .rounding(1.0)
-                        .fill(egui::Color32::from_rgb(0, 136, 84))
-                        .show(ui, |ui| {
-                            ui.spacing_mut().item_spacing = egui::Vec2::new(0.0, 0.0);
-
-                            // spawn buttons
-                            for y in 0..20 {
-                                ui.horizontal(|ui| {
-                                    for x in 0..20 {
-                                        
-                                        let text = match map.get_point(&Point { x, y }) {
-                                            _ if matches!(edit, EditOption::PlayGame) => " ".to_owned(),
-                                            Some(a) => a.symbol(),
-
-                                            _ => " ".to_owned(),
-                                        };
-                                        if ui
-                                            .add_sized(
-                                                [20.0, 20.0],
-                                                egui::Button::new(text)
-                                                    .small()
-                                                    .rounding(match map.get_point(&Point { x, y }) {
-                                                        Some(GameObject::Hole(_)) => 20.0,
-                                                        _ => 0.0
-                                                    })
-                                                    .stroke(match map.get_point(&Point { x, y }) {
-                                                        Some(GameObject::Hole(_)) => Stroke::new(2.0, egui::Color32::from_rgb(255, 0, 0)),
-                                                        _ => Stroke::new(0.0, egui::Color32::from_rgb(0, 0, 0))
-                                                    })
-                                                    .min_size(egui::Vec2::new(20.0, 20.0))
-                                                    .fill(match 
-                                                        map.get_point(&Point { x, y}) {
-                                                            Some(GameObject::Wall { a:_, b:_ }) => egui::Color32::from_rgb(0, 0, 0),
-                                                            _ => egui::Color32::from_rgb(0, 136, 84)
-                                                        }
-                                                    ),
-                                            )
-                                            .clicked()
-                                        {
-                                            clicked_point = Some(Point { x, y });
-                                        }
-                                    }
+                    if let (Some(ghost), true, EditOption::PlayGame) = (&ghost, *show_ghost, &edit)
+                    {
+                        for player in &ghost.players {
+                            render::paint_ball(
+                                &painter,
+                                green_rect.left_top(),
+                                &player.ball,
+                                render::GHOST_COLOUR,
+                            );
+                        }
+                    }
+                    for (i, player) in players.iter().enumerate() {
+                        if !player.teed_off() && i != *current {
+                            continue;
+                        }
+                        let colour = render::BALL_COLOURS[i % render::BALL_COLOURS.len()];
+                        render::paint_ball(&painter, green_rect.left_top(), &player.ball, colour);
+                        // point out whose turn it is
+                        if i == *current && players.len() > 1 && !*shot_in_play && !player.ball.sunk
+                        {
+                            painter.circle_stroke(
+                                green_rect.left_top() + player.ball.pos.into(),
+                                12.0,
+                                egui::Stroke::new(2.0, colour),
+                            );
+                        }
+                    }
+
+                    if let Some(Some(shot)) = hint {
+                        let ball = &players[*current].ball;
+                        render::paint_hint(
+                            &painter,
+                            green_rect.left_top(),
+                            map,
+                            ball,
+                            shot.velocity,
+                            *tick,
+                        );
+                    }
+                    let player = &mut players[*current];
+                    let ball = &mut player.ball;
+                    let ball_pos = green_rect.left_top() + ball.pos.into();
+                    if let Some(pointer) = response.hover_pos() {
+                        if ball_pos.distance(pointer) < AIM_REACH
+                            && ball.at_rest()
+                            && !ball.sunk
+                            && !*shot_in_play
+                            && matches!(edit, EditOption::PlayGame)
+                        {
+                            ui.output_mut(|o| {
+                                o.cursor_icon = egui::CursorIcon::Crosshair;
+                            });
+                            let mut clone = ball.clone();
+                            clone.vel = Pos::new(
+                                -(pointer.x - ball_pos.x) * AIM_STRENGTH,
+                                -(pointer.y - ball_pos.y) * AIM_STRENGTH,
+                            );
+                            // only clicks that land on the green, not on a window in front of it
+                            if response.clicked() {
+                                recording.shots.push(Shot {
+                                    player: *current,
+                                    tick: *tick,
+                                    start: ball.pos,
+                                    velocity: clone.vel,
                                 });
+                                player.shoot(clone.vel);
+                                *shot_in_play = true;
+                                *hint = None;
+                                // a hint still being looked for would be for where the ball was
+                                if matches!(search, Some((_, SearchFor::Hint { .. }))) {
+                                    *search = None;
+                                }
                             }
-                        });
-                    ui.label("move me");
-                })
-                .response
-                .rect;
-
-            
-
-            let ball_pos = green_rect.left_top() + ball.pos.into();
-            let painter = ui
-                .painter()
-                .with_clip_rect(green_rect)
-                .with_layer_id(egui::LayerId {
-                    order: egui::Order::Tooltip,
-                    id: egui::Id::new("ball"),
-                });
-            painter.circle(
-                ball_pos,
-                8.0,
-                egui::Color32::WHITE,
-                egui::Stroke::new(1.0, egui::Color32::BLACK),
-            );
-
-            if let Some(pointer) = ctx.pointer_latest_pos() {
-                if ball_pos.distance(pointer) < 80.0
-                    && ball.vel.velocity() < 1.0
-                    && matches!(edit, EditOption::PlayGame)
-                {
-                    ui.output_mut(|o| {
-                        o.cursor_icon = egui::CursorIcon::Crosshair;
-                    });
-                    let mut clone = ball.clone();
-                    clone.vel = Pos::new(
-                        -(pointer.x - ball_pos.x) * 12.0,
-                        -(pointer.y - ball_pos.y) * 12.0,
-                    );
-                    if clicked {
-                        *ball = clone;
+                            render::paint_preview(
+                                &painter,
+                                green_rect.left_top(),
+                                map,
+                                clone,
+                                *tick,
+                                render::PREVIEW_COLOUR,
+                            );
+                        }
                     }
-                    for i in (0..14).rev() {
-                        for _ in 0..3 {
-                            clone.update_pos(&map, delta)
+                    if let (EditOption::EditMap(tool), Some(pointer)) =
+                        (&edit, response.hover_pos())
+                    {
+                        let ghost = place_on_rail_grid(*tool, spot_under(pointer))
+                            .and_then(|o| o.shape(map.cell_size));
+                        if let Some(shape) = ghost {
+                            render::paint_shape(
+                                &painter,
+                                green_rect.left_top(),
+                                shape,
+                                Color32::from_black_alpha(100),
+                            );
                         }
-                        painter.circle_filled(
-                            green_rect.left_top() + clone.pos.into(),
-                            (i as f32 / 4.0) + 4.0,
-                            egui::Color32::from_rgba_premultiplied(180, 180, 180, 180),
-                        );
                     }
-                }
-            }
-            match edit {
-                EditOption::EditMap(GameObject::Wall {a:c, b:_}) if !matches!(c, Point {x:-1,y:-1}) => {
-                    let x1 = c.x as f32*20.0 + green_rect.left()+10.0;
-                    let y1 = c.y as f32*20.0 + green_rect.top()+10.0;
-                    let x2 = ((ctx.pointer_hover_pos().unwrap_or_default().x - green_rect.left())/20.0).floor()*20.0+10.0 + green_rect.left();
-                    let y2 = ((ctx.pointer_hover_pos().unwrap_or_default().y - green_rect.top())/20.0).floor()*20.0+10.0 + green_rect.top();
-                    painter.rect(Rect { min: Pos2 { x:x1.min(x2), y:y1.min(y2) }, max:  Pos2 { x:x1.max(x2), y:y1.max(y2) } }, 3.0, Color32::from_black_alpha(100), Stroke::new(10.0, Color32::from_black_alpha(100)));
-                }
-                _ => {}
-            }
+                    match (&edit, hover) {
+                        (
+                            EditOption::EditMap(
+                                GameObject::Wall { a: c, b: _ }
+                                | GameObject::Surface { a: c, .. }
+                                | GameObject::Conveyor { a: c, .. },
+                            ),
+                            Some(end),
+                        ) if !matches!(c, Point { x: -1, y: -1 }) => {
+                            let a = render::cell_rect(map, green_rect.left_top(), *c);
+                            let b = render::cell_rect(map, green_rect.left_top(), end);
+                            painter.rect(
+                                Rect::from_two_pos(a.center(), b.center()),
+                                3.0,
+                                Color32::from_black_alpha(100),
+                                Stroke::new(map.cell_size / 2.0, Color32::from_black_alpha(100)),
+                            );
+                        }
+                        _ => {}
+                    }
+                });
         });
-        if ball.vel.velocity() > 1.0 {
+        let ghost_moving = *show_ghost && ghost.as_ref().map_or(false, |g| g.tick < g.length);
+        if !players.iter().all(|p| p.ball.at_rest()) || map.has_moving_obstacles() || ghost_moving {
             ctx.request_repaint_after(Duration::from_millis(16));
         }
 
+        // the rail, bumper or curved wall tool, taken now as `edit` is borrowed until the clicks are handled
+        let rail_grid_tool = match edit {
+            EditOption::EditMap(
+                tool @ (GameObject::Rail { .. }
+                | GameObject::Bumper { .. }
+                | GameObject::Arc { .. }),
+            ) => Some(*tool),
+            _ => None,
+        };
 
         // if a point was clicked, the it is handled here, outside the egui context
         if let Some(pt) = clicked_point {
             match &edit {
                 EditOption::EditMap(GameObject::Start(_)) => {
-                    map.objects.retain(|i| !matches!(i, GameObject::Start(_)));
-                    map.objects.push(GameObject::Start(pt));
-                    self.reset();
+                    self.edit_map(EditCommand::place(self.map(), GameObject::Start(pt)));
                 }
                 EditOption::EditMap(GameObject::Hole(_)) => {
-                    map.objects.retain(|i| !matches!(i, GameObject::Hole(_)));
-                    map.objects.push(GameObject::Hole(pt));
-                    self.reset();
+                    self.edit_map(EditCommand::place(self.map(), GameObject::Hole(pt)));
                 }
 
                 EditOption::EditMap(GameObject::Height {
@@ -813,14 +2152,139 @@ impl eframe::App for App {
                     });
                 }
 
-                EditOption::EditMap(GameObject::Height { a, b: _ , height:h}) => {
-                    self.map.add_object(GameObject::Height { a: *a, b: pt, height: self.slider });
+                EditOption::EditMap(GameObject::Height { a, b: _, height: h }) => {
+                    let height = GameObject::Height {
+                        a: *a,
+                        b: pt,
+                        height: self.slider,
+                    };
                     self.edit = EditOption::EditMap(GameObject::Height {
                         a: Point { x: -1, y: -1 },
                         b: Point { x: -1, y: -1 },
                         height: self.slider,
                     });
-                    self.reset();
+                    self.edit_map(EditCommand::add(self.map(), height));
+                }
+
+                EditOption::EditMap(GameObject::Surface {
+                    a: Point { x: -1, y: -1 },
+                    b: _,
+                    surface,
+                }) => {
+                    self.edit = EditOption::EditMap(GameObject::Surface {
+                        a: pt,
+                        b: Point { x: -1, y: -1 },
+                        surface: *surface,
+                    });
+                }
+
+                EditOption::EditMap(GameObject::Surface { a, .. }) => {
+                    let surface = GameObject::Surface {
+                        a: *a,
+                        b: pt,
+                        surface: self.surface,
+                    };
+                    self.edit = EditOption::EditMap(GameObject::Surface {
+                        a: Point { x: -1, y: -1 },
+                        b: Point { x: -1, y: -1 },
+                        surface: self.surface,
+                    });
+                    self.edit_map(EditCommand::add(self.map(), surface));
+                }
+
+                EditOption::EditMap(GameObject::SlidingBlock {
+                    a: Point { x: -1, y: -1 },
+                    b: _,
+                    timing,
+                }) => {
+                    self.edit = EditOption::EditMap(GameObject::SlidingBlock {
+                        a: pt,
+                        b: Point { x: -1, y: -1 },
+                        timing: *timing,
+                    });
+                }
+
+                EditOption::EditMap(GameObject::SlidingBlock { a, .. }) => {
+                    let block = GameObject::SlidingBlock {
+                        a: *a,
+                        b: pt,
+                        timing: self.timing,
+                    };
+                    self.edit = EditOption::EditMap(GameObject::SlidingBlock {
+                        a: Point { x: -1, y: -1 },
+                        b: Point { x: -1, y: -1 },
+                        timing: self.timing,
+                    });
+                    self.edit_map(EditCommand::add(self.map(), block));
+                }
+
+                EditOption::EditMap(GameObject::Teleporter {
+                    a: Point { x: -1, y: -1 },
+                    b: _,
+                    turn,
+                }) => {
+                    self.edit = EditOption::EditMap(GameObject::Teleporter {
+                        a: pt,
+                        b: Point { x: -1, y: -1 },
+                        turn: *turn,
+                    });
+                }
+
+                EditOption::EditMap(GameObject::Teleporter { a, .. }) => {
+                    let teleporter = GameObject::Teleporter {
+                        a: *a,
+                        b: pt,
+                        turn: self.turn,
+                    };
+                    self.edit = EditOption::EditMap(GameObject::Teleporter {
+                        a: Point { x: -1, y: -1 },
+                        b: Point { x: -1, y: -1 },
+                        turn: self.turn,
+                    });
+                    self.edit_map(EditCommand::add(self.map(), teleporter));
+                }
+
+                EditOption::EditMap(GameObject::Booster { .. }) => {
+                    let booster = GameObject::Booster {
+                        pos: pt,
+                        direction: self.direction,
+                    };
+                    self.edit_map(EditCommand::add(self.map(), booster));
+                }
+
+                EditOption::EditMap(GameObject::Conveyor {
+                    a: Point { x: -1, y: -1 },
+                    b: _,
+                    direction,
+                }) => {
+                    self.edit = EditOption::EditMap(GameObject::Conveyor {
+                        a: pt,
+                        b: Point { x: -1, y: -1 },
+                        direction: *direction,
+                    });
+                }
+
+                EditOption::EditMap(GameObject::Conveyor { a, .. }) => {
+                    let conveyor = GameObject::Conveyor {
+                        a: *a,
+                        b: pt,
+                        direction: self.direction,
+                    };
+                    self.edit = EditOption::EditMap(GameObject::Conveyor {
+                        a: Point { x: -1, y: -1 },
+                        b: Point { x: -1, y: -1 },
+                        direction: self.direction,
+                    });
+                    self.edit_map(EditCommand::add(self.map(), conveyor));
+                }
+
+                EditOption::EditMap(GameObject::Windmill { .. }) => {
+                    let windmill = GameObject::Windmill {
+                        pivot: pt,
+                        arm: self.arm,
+                        timing: self.timing,
+                    };
+                    self.edit_map(EditCommand::add(self.map(), windmill));
                 }
 
                 EditOption::EditMap(GameObject::Wall {
@@ -834,21 +2298,88 @@ impl eframe::App for App {
                 }
 
                 EditOption::EditMap(GameObject::Wall { a, b: _ }) => {
-                    self.map.add_object(GameObject::Wall { a: *a, b: pt });
+                    let wall = GameObject::Wall { a: *a, b: pt };
                     self.edit = EditOption::EditMap(GameObject::Wall {
                         a: Point { x: -1, y: -1 },
                         b: Point { x: -1, y: -1 },
                     });
-                    self.reset();
+                    self.edit_map(EditCommand::add(self.map(), wall));
                 }
                 EditOption::Delete => {
-                    self.map.objects.retain(|f| !f.get_points().contains(&pt));
-                    self.reset();
+                    let removed =
+                        EditCommand::remove_where(self.map(), |f| f.get_points().contains(&pt));
+                    self.edit_map(EditCommand::Batch(removed));
                 }
-                
+                EditOption::Select => self.selected = self.map().object_at(&pt),
 
                 _ => {}
             }
         }
+
+        // rails, bumpers and curved walls go on their own finer grid rather than in cells
+        if let (Some(spot), Some(tool)) = (clicked_spot, rail_grid_tool) {
+            let unset = Point { x: -1, y: -1 };
+            if let Some(object) = place_on_rail_grid(tool, spot) {
+                // start the next rail or curved wall afresh
+                self.edit = EditOption::EditMap(match tool {
+                    GameObject::Rail { thickness, .. } => GameObject::Rail {
+                        a: unset,
+                        b: unset,
+                        thickness,
+                    },
+                    GameObject::Arc {
+                        radius,
+                        from,
+                        sweep,
+                        thickness,
+                        ..
+                    } => GameObject::Arc {
+                        centre: unset,
+                        radius,
+                        from,
+                        sweep,
+                        thickness,
+                    },
+                    _ => tool,
+                });
+                self.edit_map(EditCommand::add(self.map(), object));
+            } else {
+                self.edit = EditOption::EditMap(match tool {
+                    GameObject::Rail { a, b, thickness } if a == unset => GameObject::Rail {
+                        a: spot,
+                        b,
+                        thickness,
+                    },
+                    GameObject::Arc {
+                        centre,
+                        radius,
+                        from,
+                        sweep,
+                        thickness,
+                    } if centre == unset => GameObject::Arc {
+                        centre: spot,
+                        radius,
+                        from,
+                        sweep,
+                        thickness,
+                    },
+                    _ => tool,
+                });
+            }
+        }
+
+        if let Some(pt) = drag_from {
+            self.start_drag(pt);
+        }
+        if let Some(pt) = dragged_to {
+            self.drag_to(pt);
+        }
+        if drag_released {
+            self.end_drag();
+        }
+
+        if just_finished {
+            self.finish_hole(now);
+        }
     }
 }
@@ -0,0 +1,353 @@
+use crate::map::{GameObject, GolfMap, Point};
+
+/// most edits kept to undo, the oldest are forgotten first
+const MAX_UNDO: usize = 200;
+
+/// The settings of a hole that aren't objects on the green.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Properties {
+    pub(crate) name: String,
+    pub(crate) par: u32,
+    pub(crate) restitution: f32,
+    pub(crate) cell_size: f32,
+}
+
+impl Properties {
+    pub(crate) fn of(map: &GolfMap) -> Properties {
+        Properties {
+            name: map.name.clone(),
+            par: map.par,
+            restitution: map.restitution,
+            cell_size: map.cell_size,
+        }
+    }
+
+    fn set(&self, map: &mut GolfMap) {
+        map.name = self.name.clone();
+        map.par = self.par;
+        map.restitution = self.restitution;
+        map.cell_size = self.cell_size;
+    }
+}
+
+/// One change to a map made in the editor, which can be done again or undone.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum EditCommand {
+    Add {
+        index: usize,
+        object: GameObject,
+    },
+    Remove {
+        index: usize,
+        object: GameObject,
+    },
+    Modify {
+        index: usize,
+        before: GameObject,
+        after: GameObject,
+    },
+    /// changes the size of the green, anything off the new edge has to be removed first
+    Resize {
+        before: (i32, i32),
+        after: (i32, i32),
+    },
+    /// changes the name, par and other settings of the hole
+    SetProperties {
+        before: Properties,
+        after: Properties,
+    },
+    /// several commands done in order and undone as one
+    Batch(Vec<EditCommand>),
+}
+
+impl EditCommand {
+    /// Removes every object `remove` picks out, back to front so each index stays right.
+    pub(crate) fn remove_where(
+        map: &GolfMap,
+        remove: impl Fn(&GameObject) -> bool,
+    ) -> Vec<EditCommand> {
+        map.objects
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, object)| remove(object))
+            .map(|(index, object)| EditCommand::Remove {
+                index,
+                object: *object,
+            })
+            .collect()
+    }
+
+    /// Adds an object to the end of the map.
+    pub(crate) fn add(map: &GolfMap, object: GameObject) -> EditCommand {
+        EditCommand::Add {
+            index: map.objects.len(),
+            object,
+        }
+    }
+
+    /// Puts a start or hole on the map in place of any that are already there.
+    pub(crate) fn place(map: &GolfMap, object: GameObject) -> EditCommand {
+        let same_kind =
+            |o: &GameObject| std::mem::discriminant(o) == std::mem::discriminant(&object);
+        let mut commands = EditCommand::remove_where(map, same_kind);
+        let index = map.objects.len() - commands.len();
+        commands.push(EditCommand::Add { index, object });
+        EditCommand::Batch(commands)
+    }
+
+    /// Changes the size of the green, taking off anything that won't fit first.
+    pub(crate) fn resize(map: &GolfMap, width: i32, height: i32) -> EditCommand {
+        let fits = |p: &Point| (0..width).contains(&p.x) && (0..height).contains(&p.y);
+        let mut commands = EditCommand::remove_where(map, |o| !o.get_points().iter().all(fits));
+        commands.push(EditCommand::Resize {
+            before: (map.width, map.height),
+            after: (width, height),
+        });
+        EditCommand::Batch(commands)
+    }
+
+    /// Empties the map down to a start in the top left and a hole in the bottom right.
+    pub(crate) fn clear(map: &GolfMap) -> EditCommand {
+        let mut commands = EditCommand::remove_where(map, |_| true);
+        for (index, object) in [
+            GameObject::Start(Point { x: 1, y: 1 }),
+            GameObject::Hole(Point {
+                x: map.width - 2,
+                y: map.height - 2,
+            }),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            commands.push(EditCommand::Add { index, object });
+        }
+        EditCommand::Batch(commands)
+    }
+
+    /// Swaps everything on the map for what is on `with`, its settings too.
+    pub(crate) fn replace(map: &GolfMap, with: &GolfMap) -> EditCommand {
+        let mut commands = EditCommand::remove_where(map, |_| true);
+        commands.push(EditCommand::Resize {
+            before: (map.width, map.height),
+            after: (with.width, with.height),
+        });
+        for (index, object) in with.objects.iter().enumerate() {
+            commands.push(EditCommand::Add {
+                index,
+                object: *object,
+            });
+        }
+        commands.push(EditCommand::SetProperties {
+            before: Properties::of(map),
+            after: Properties::of(with),
+        });
+        EditCommand::Batch(commands)
+    }
+
+    /// whether doing this would leave the map just as it was
+    pub(crate) fn is_empty(&self) -> bool {
+        match self {
+            EditCommand::Modify { before, after, .. } => before == after,
+            EditCommand::SetProperties { before, after } => before == after,
+            EditCommand::Batch(commands) => commands.iter().all(EditCommand::is_empty),
+            _ => false,
+        }
+    }
+
+    /// the command that puts things back how they were before this one
+    fn inverse(&self) -> EditCommand {
+        match self {
+            EditCommand::Add { index, object } => EditCommand::Remove {
+                index: *index,
+                object: *object,
+            },
+            EditCommand::Remove { index, object } => EditCommand::Add {
+                index: *index,
+                object: *object,
+            },
+            EditCommand::Modify {
+                index,
+                before,
+                after,
+            } => EditCommand::Modify {
+                index: *index,
+                before: *after,
+                after: *before,
+            },
+            EditCommand::Resize { before, after } => EditCommand::Resize {
+                before: *after,
+                after: *before,
+            },
+            EditCommand::SetProperties { before, after } => EditCommand::SetProperties {
+                before: after.clone(),
+                after: before.clone(),
+            },
+            EditCommand::Batch(commands) => {
+                EditCommand::Batch(commands.iter().rev().map(EditCommand::inverse).collect())
+            }
+        }
+    }
+
+    fn apply(&self, map: &mut GolfMap) {
+        // fall back to looking for the object if it isn't quite where it was,
+        // in case something has changed the map without going through the history
+        let find = |map: &GolfMap, index: usize, object: &GameObject| {
+            if map.objects.get(index) == Some(object) {
+                Some(index)
+            } else {
+                map.objects.iter().position(|o| o == object)
+            }
+        };
+        match self {
+            EditCommand::Add { index, object } => {
+                map.objects.insert((*index).min(map.objects.len()), *object);
+            }
+            EditCommand::Remove { index, object } => {
+                if let Some(i) = find(map, *index, object) {
+                    map.objects.remove(i);
+                }
+            }
+            EditCommand::Modify {
+                index,
+                before,
+                after,
+            } => {
+                if let Some(i) = find(map, *index, before) {
+                    map.objects[i] = *after;
+                }
+            }
+            EditCommand::Resize {
+                after: (width, height),
+                ..
+            } => map.resize(*width, *height),
+            EditCommand::SetProperties { after, .. } => after.set(map),
+            EditCommand::Batch(commands) => {
+                for command in commands {
+                    command.apply(map);
+                }
+            }
+        }
+    }
+}
+
+/// The edits made to the hole being edited, so they can be undone and redone.
+#[derive(Default)]
+pub(crate) struct History {
+    undo: Vec<EditCommand>,
+    redo: Vec<EditCommand>,
+}
+
+impl History {
+    /// Makes an edit to the map and remembers it, anything that was undone can't be redone after this.
+    pub(crate) fn apply(&mut self, map: &mut GolfMap, command: EditCommand) {
+        if command.is_empty() {
+            return;
+        }
+        command.apply(map);
+        map.update_hashmap();
+        map.update_heightmap();
+        self.record(command);
+    }
+
+    /// Remembers an edit that has already been made to the map, like an object dragged into place.
+    pub(crate) fn record(&mut self, command: EditCommand) {
+        if self.undo.len() == MAX_UNDO {
+            self.undo.remove(0);
+        }
+        self.undo.push(command);
+        self.redo.clear();
+    }
+
+    /// Undoes the last edit, returning false if there was nothing to undo.
+    pub(crate) fn undo(&mut self, map: &mut GolfMap) -> bool {
+        let Some(command) = self.undo.pop() else {
+            return false;
+        };
+        command.inverse().apply(map);
+        map.update_hashmap();
+        map.update_heightmap();
+        self.redo.push(command);
+        true
+    }
+
+    /// Does the last undone edit again, returning false if there was nothing to redo.
+    pub(crate) fn redo(&mut self, map: &mut GolfMap) -> bool {
+        let Some(command) = self.redo.pop() else {
+            return false;
+        };
+        command.apply(map);
+        map.update_hashmap();
+        map.update_heightmap();
+        self.undo.push(command);
+        true
+    }
+
+    pub(crate) fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub(crate) fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Forgets every edit, for when a different map is loaded.
+    pub(crate) fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replacing_the_map_can_be_undone() {
+        let mut map = GolfMap::default();
+        map.name = "Old".to_owned();
+        map.objects.push(GameObject::Hole(Point { x: 5, y: 5 }));
+        map.update_hashmap();
+        let old_code = map.to_text();
+
+        let mut with = GolfMap::default();
+        with.name = "New".to_owned();
+        with.width = 30;
+        with.par = 5;
+        with.restitution = 0.5;
+        with.cell_size = 16.0;
+        with.objects = vec![
+            GameObject::Start(Point { x: 25, y: 2 }),
+            GameObject::Hole(Point { x: 28, y: 18 }),
+        ];
+        let new_code = with.to_text();
+
+        let mut history = History::default();
+        let replace = EditCommand::replace(&map, &with);
+        history.apply(&mut map, replace);
+        assert_eq!(map.to_text(), new_code);
+        assert_eq!(map.name, "New");
+
+        assert!(history.undo(&mut map));
+        assert_eq!(map.to_text(), old_code);
+        assert_eq!(map.name, "Old");
+
+        assert!(history.redo(&mut map));
+        assert_eq!(map.to_text(), new_code);
+    }
+
+    #[test]
+    fn unchanged_properties_are_not_an_edit() {
+        let mut map = GolfMap::default();
+        let mut history = History::default();
+        let properties = Properties::of(&map);
+        history.apply(
+            &mut map,
+            EditCommand::SetProperties {
+                before: properties.clone(),
+                after: properties,
+            },
+        );
+        assert!(!history.can_undo());
+    }
+}
@@ -1,4 +1,18 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
 mod app;
+mod history;
+mod library;
+mod map;
+mod map_code;
+mod physics;
+mod players;
+mod render;
+mod replay;
+mod simulation;
+mod solver;
 pub use app::App;
+pub use map_code::MapCodeError;
+pub use physics::Pos;
+pub use simulation::{ShotReport, Simulation};
+pub use solver::{PlannedShot, Solution, SolverSettings};
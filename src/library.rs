@@ -0,0 +1,106 @@
+use crate::map::GolfMap;
+
+/// the library is stored under its own key, apart from the rest of the app
+pub(crate) const LIBRARY_KEY: &str = "map_library";
+
+/// a map kept in the library
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+#[serde(default)]
+pub(crate) struct SavedMap {
+    pub(crate) title: String,
+    pub(crate) author: String,
+    /// when the map was last saved, in seconds since 1970
+    pub(crate) saved_at: f64,
+    /// the map itself, kept as a map code so old saves still open as the format changes
+    pub(crate) code: String,
+}
+
+impl Default for SavedMap {
+    fn default() -> Self {
+        Self {
+            title: "Untitled".to_owned(),
+            author: String::new(),
+            saved_at: 0.0,
+            code: String::new(),
+        }
+    }
+}
+
+/// Maps saved on this device, newest last.
+#[derive(serde::Deserialize, serde::Serialize, Default)]
+#[serde(default)]
+pub(crate) struct Library {
+    pub(crate) maps: Vec<SavedMap>,
+}
+
+impl Library {
+    pub(crate) fn load(storage: &dyn eframe::Storage) -> Self {
+        eframe::get_value(storage, LIBRARY_KEY).unwrap_or_default()
+    }
+
+    pub(crate) fn save(&self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, LIBRARY_KEY, self);
+    }
+
+    /// Saves a map as a new entry named after it.
+    pub(crate) fn add(&mut self, map: &GolfMap, author: &str) {
+        self.maps.push(SavedMap {
+            title: map.name.clone(),
+            author: author.to_owned(),
+            saved_at: now(),
+            code: map.to_text(),
+        });
+    }
+
+    /// Saves a map over an entry, keeping its title and author.
+    pub(crate) fn overwrite(&mut self, index: usize, map: &GolfMap) {
+        if let Some(saved) = self.maps.get_mut(index) {
+            saved.code = map.to_text();
+            saved.saved_at = now();
+        }
+    }
+
+    /// Copies an entry to just after itself.
+    pub(crate) fn duplicate(&mut self, index: usize) {
+        if let Some(saved) = self.maps.get(index) {
+            let mut copy = saved.clone();
+            copy.title = format!("{} (copy)", copy.title);
+            copy.saved_at = now();
+            self.maps.insert(index + 1, copy);
+        }
+    }
+
+    pub(crate) fn remove(&mut self, index: usize) {
+        if index < self.maps.len() {
+            self.maps.remove(index);
+        }
+    }
+}
+
+/// the time right now, in seconds since 1970
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn now() -> f64 {
+    js_sys::Date::now() / 1000.0
+}
+
+/// the time right now, in seconds since 1970
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn now() -> f64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0.0, |d| d.as_secs_f64())
+}
+
+/// how long ago something was saved, like "5 minutes ago"
+pub(crate) fn age_text(saved_at: f64, now: f64) -> String {
+    let seconds = (now - saved_at).max(0.0) as u64;
+    let (count, unit) = match seconds {
+        0..=59 => return "just now".to_owned(),
+        60..=3599 => (seconds / 60, "minute"),
+        3600..=86_399 => (seconds / 3600, "hour"),
+        86_400..=2_591_999 => (seconds / 86_400, "day"),
+        2_592_000..=31_535_999 => (seconds / 2_592_000, "month"),
+        _ => (seconds / 31_536_000, "year"),
+    };
+    format!("{count} {unit}{} ago", if count == 1 { "" } else { "s" })
+}
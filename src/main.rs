@@ -7,7 +7,7 @@ fn main() -> eframe::Result<()> {
     env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`).
 
     let native_options = eframe::NativeOptions::default();
-    
+
     eframe::run_native(
         "mini_golf",
         native_options,
@@ -22,8 +22,7 @@ fn main() {
     eframe::WebLogger::init(log::LevelFilter::Debug).ok();
 
     let web_options = eframe::WebOptions::default();
-    
-    
+
     wasm_bindgen_futures::spawn_local(async {
         eframe::WebRunner::new()
             .start(
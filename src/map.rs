@@ -0,0 +1,865 @@
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+
+use image::imageops::FilterType;
+use image::{DynamicImage, Rgb, RgbImage};
+use strum_macros::EnumIter;
+
+use crate::physics::{Hit, MovingShape, Pos, Shape, PHYSICS_DT};
+
+/// sizes a green can be in cells, along either side
+pub(crate) const GRID_SIZES: RangeInclusive<i32> = 2..=100;
+/// sizes a cell can be in pixels
+pub(crate) const CELL_SIZES: RangeInclusive<f32> = 4.0..=64.0;
+
+/// how long a moving obstacle can take to go round once, in tenths of a second
+pub(crate) const PERIODS: RangeInclusive<i32> = 5..=600;
+/// how far a windmill's sails reach out from the middle, in cells
+pub(crate) const ARM_LENGTHS: RangeInclusive<i32> = 1..=20;
+/// how thick a windmill's sails are either side of their middle, as a share of a cell
+const SAIL_THICKNESS: f32 = 0.2;
+/// rail ends sit on a finer grid than the cells, this many steps to a cell
+pub(crate) const RAIL_STEPS: i32 = 10;
+/// how thick a rail or curved wall can be, in rail steps
+pub(crate) const RAIL_THICKNESSES: RangeInclusive<i32> = 1..=10;
+/// sizes a bumper can be from its middle to its edge, in rail steps
+pub(crate) const BUMPER_RADII: RangeInclusive<i32> = 2..=30;
+/// how much of its speed the ball keeps bouncing off a bumper, in percent, over 100 kicks it away faster
+pub(crate) const BUMPER_BOUNCES: RangeInclusive<i32> = 0..=200;
+/// sizes a curved wall can be from its middle to its line, in rail steps
+pub(crate) const ARC_RADII: RangeInclusive<i32> = 1..=1000;
+/// how far round a curved wall goes, in degrees
+pub(crate) const ARC_SWEEPS: RangeInclusive<i32> = 1..=360;
+
+/// heightmap pixels along each side of a cell
+const HEIGHTMAP_CELL_PIXELS: u32 = 10;
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, PartialEq, Eq, EnumIter)]
+pub(crate) enum GameObject {
+    Hole(Point),
+    Wall {
+        a: Point,
+        b: Point,
+    },
+    Start(Point),
+    Height {
+        a: Point,
+        b: Point,
+        height: i32,
+    },
+    /// a rectangle of ground that isn't ordinary grass
+    Surface {
+        a: Point,
+        b: Point,
+        surface: Surface,
+    },
+    /// a block one cell across that slides from `a` to `b` and back again
+    SlidingBlock {
+        a: Point,
+        b: Point,
+        timing: Timing,
+    },
+    /// a pair of sails reaching `arm` cells out from `pivot` either side, going round clockwise
+    Windmill {
+        pivot: Point,
+        arm: i32,
+        timing: Timing,
+    },
+    /// a pair of linked pads, a ball rolling onto either comes out of the other
+    /// turned `turn` quarter turns clockwise
+    Teleporter {
+        a: Point,
+        b: Point,
+        turn: i32,
+    },
+    /// a pad that gives the ball a kick along `direction` as it rolls on
+    Booster {
+        pos: Point,
+        direction: Direction,
+    },
+    /// a rectangle of belt that carries the ball along `direction` while it is on it
+    Conveyor {
+        a: Point,
+        b: Point,
+        direction: Direction,
+    },
+    /// a thin wall from `a` to `b` at any angle, with its ends and `thickness` measured in rail steps
+    Rail {
+        a: Point,
+        b: Point,
+        thickness: i32,
+    },
+    /// a round post the ball bounces off keeping `bounce` percent of its speed,
+    /// with its middle and `radius` measured in rail steps
+    Bumper {
+        centre: Point,
+        radius: i32,
+        bounce: i32,
+    },
+    /// a curved wall `radius` out from `centre`, going clockwise for `sweep` degrees from `from` degrees
+    /// (0 is to the right), with its middle, `radius` and `thickness` measured in rail steps
+    Arc {
+        centre: Point,
+        radius: i32,
+        from: i32,
+        sweep: i32,
+        thickness: i32,
+    },
+}
+
+/// The eight ways an arrow on the green can point, clockwise from right.
+#[derive(
+    serde::Deserialize, serde::Serialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash, EnumIter,
+)]
+pub(crate) enum Direction {
+    #[default]
+    Right,
+    DownRight,
+    Down,
+    DownLeft,
+    Left,
+    UpLeft,
+    Up,
+    UpRight,
+}
+
+impl Direction {
+    /// a unit vector pointing this way, with y down the screen
+    pub(crate) fn vector(&self) -> Pos {
+        let diagonal = std::f32::consts::FRAC_1_SQRT_2;
+        match self {
+            Direction::Right => Pos::new(1.0, 0.0),
+            Direction::DownRight => Pos::new(diagonal, diagonal),
+            Direction::Down => Pos::new(0.0, 1.0),
+            Direction::DownLeft => Pos::new(-diagonal, diagonal),
+            Direction::Left => Pos::new(-1.0, 0.0),
+            Direction::UpLeft => Pos::new(-diagonal, -diagonal),
+            Direction::Up => Pos::new(0.0, -1.0),
+            Direction::UpRight => Pos::new(diagonal, -diagonal),
+        }
+    }
+
+    pub(crate) fn arrow(&self) -> &'static str {
+        match self {
+            Direction::Right => "➡",
+            Direction::DownRight => "⬊",
+            Direction::Down => "⬇",
+            Direction::DownLeft => "⬋",
+            Direction::Left => "⬅",
+            Direction::UpLeft => "⬉",
+            Direction::Up => "⬆",
+            Direction::UpRight => "⬈",
+        }
+    }
+}
+
+/// When a moving obstacle is where along its loop.
+///
+/// Obstacles are moved by the physics step count rather than the clock, so the same
+/// step always puts them in the same place.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub(crate) struct Timing {
+    /// how long one loop takes, in tenths of a second
+    pub(crate) period: i32,
+    /// how far through its loop the obstacle is when the hole starts, in percent
+    pub(crate) phase: i32,
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        Self {
+            period: 40,
+            phase: 0,
+        }
+    }
+}
+
+impl Timing {
+    pub(crate) fn seconds(&self) -> f32 {
+        self.period.max(1) as f32 / 10.0
+    }
+
+    /// how far through its loop the obstacle is after `tick` physics steps, from 0 up to 1
+    pub(crate) fn fraction(&self, tick: u32) -> f32 {
+        let steps = ((self.seconds() / PHYSICS_DT).round() as u32).max(1);
+        let offset = self.phase.clamp(0, 99) as u32 * steps / 100;
+        ((tick % steps + offset) % steps) as f32 / steps as f32
+    }
+}
+
+/// the kinds of ground a `GameObject::Surface` can be
+#[derive(
+    serde::Deserialize, serde::Serialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash, EnumIter,
+)]
+pub(crate) enum Surface {
+    /// slows the ball down hard
+    #[default]
+    Sand,
+    /// hardly slows the ball at all
+    Ice,
+    /// slower than grass, faster than sand
+    Rough,
+    /// costs a stroke and puts the ball back where it was hit from
+    Water,
+}
+
+impl Surface {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Surface::Sand => "Sand",
+            Surface::Ice => "Ice",
+            Surface::Rough => "Rough",
+            Surface::Water => "Water",
+        }
+    }
+
+    /// shown on the green in the editor, none of them the same as another object's
+    pub(crate) fn symbol(&self) -> &'static str {
+        match self {
+            Surface::Sand => ":",
+            Surface::Ice => "I",
+            Surface::Rough => "R",
+            Surface::Water => "~",
+        }
+    }
+}
+
+impl GameObject {
+    pub(crate) fn symbol(&self) -> String {
+        match &self {
+            GameObject::Hole(_) => "H".to_string(),
+            GameObject::Wall { .. } => "W".to_string(),
+            GameObject::Start(_) => "S".to_string(),
+            GameObject::Height { .. } => "H".to_string(),
+            GameObject::Surface { surface, .. } => surface.symbol().to_string(),
+            GameObject::SlidingBlock { .. } => "B".to_string(),
+            GameObject::Windmill { .. } => "X".to_string(),
+            GameObject::Teleporter { .. } => "T".to_string(),
+            GameObject::Booster { direction, .. } => direction.arrow().to_string(),
+            GameObject::Conveyor { direction, .. } => direction.arrow().to_string(),
+            GameObject::Rail { .. } => "/".to_string(),
+            GameObject::Bumper { .. } => "O".to_string(),
+            GameObject::Arc { .. } => "(".to_string(),
+        }
+    }
+
+    fn point(&self) -> Option<Point> {
+        match &self {
+            GameObject::Hole(p) => Some(*p),
+            GameObject::Wall { .. } => None,
+            GameObject::Start(p) => Some(*p),
+            GameObject::Height { .. } => None,
+            GameObject::Surface { .. } => None,
+            GameObject::SlidingBlock { .. } => None,
+            GameObject::Windmill { pivot, .. } => Some(*pivot),
+            GameObject::Teleporter { .. } => None,
+            GameObject::Booster { pos, .. } => Some(*pos),
+            GameObject::Conveyor { .. } => None,
+            GameObject::Rail { .. } => None,
+            GameObject::Bumper { .. } => None,
+            GameObject::Arc { .. } => None,
+        }
+    }
+
+    pub(crate) fn get_points(&self) -> Vec<Point> {
+        match &self {
+            GameObject::Hole(p) => vec![*p],
+            GameObject::Wall { a, b } => vec![*a, *b],
+            GameObject::Start(p) => vec![*p],
+            GameObject::Height { a, b, .. } => vec![*a, *b],
+            GameObject::Surface { a, b, .. } => vec![*a, *b],
+            GameObject::SlidingBlock { a, b, .. } => vec![*a, *b],
+            GameObject::Windmill { pivot, .. } => vec![*pivot],
+            GameObject::Teleporter { a, b, .. } => vec![*a, *b],
+            GameObject::Booster { pos, .. } => vec![*pos],
+            GameObject::Conveyor { a, b, .. } => vec![*a, *b],
+            GameObject::Rail { a, b, .. } => vec![rail_cell(a), rail_cell(b)],
+            GameObject::Bumper { centre, .. } => vec![rail_cell(centre)],
+            GameObject::Arc { centre, .. } => vec![rail_cell(centre)],
+        }
+    }
+
+    /// whether the object takes up a cell
+    pub(crate) fn covers(&self, point: &Point) -> bool {
+        match self {
+            GameObject::Wall { a, b }
+            | GameObject::Height { a, b, .. }
+            | GameObject::Surface { a, b, .. }
+            | GameObject::SlidingBlock { a, b, .. }
+            | GameObject::Conveyor { a, b, .. } => {
+                (a.x.min(b.x)..=a.x.max(b.x)).contains(&point.x)
+                    && (a.y.min(b.y)..=a.y.max(b.y)).contains(&point.y)
+            }
+            GameObject::Hole(p)
+            | GameObject::Start(p)
+            | GameObject::Windmill { pivot: p, .. }
+            | GameObject::Booster { pos: p, .. } => p == point,
+            GameObject::Teleporter { a, b, .. } => a == point || b == point,
+            // any cell the shape reaches into, working in cells
+            GameObject::Rail { .. } | GameObject::Bumper { .. } | GameObject::Arc { .. } => {
+                let centre = Pos::new(point.x as f32 + 0.5, point.y as f32 + 0.5);
+                self.shape(1.0).map_or(false, |shape| {
+                    let (closest, thickness) = shape.closest(centre);
+                    (centre - closest).velocity() - thickness <= 0.5
+                })
+            }
+        }
+    }
+
+    /// the same object moved `dx` cells across and `dy` cells down
+    pub(crate) fn moved(&self, dx: i32, dy: i32) -> GameObject {
+        let m = |p: &Point| Point {
+            x: p.x + dx,
+            y: p.y + dy,
+        };
+        match self {
+            GameObject::Hole(p) => GameObject::Hole(m(p)),
+            GameObject::Wall { a, b } => GameObject::Wall { a: m(a), b: m(b) },
+            GameObject::Start(p) => GameObject::Start(m(p)),
+            GameObject::Height { a, b, height } => GameObject::Height {
+                a: m(a),
+                b: m(b),
+                height: *height,
+            },
+            GameObject::Surface { a, b, surface } => GameObject::Surface {
+                a: m(a),
+                b: m(b),
+                surface: *surface,
+            },
+            GameObject::SlidingBlock { a, b, timing } => GameObject::SlidingBlock {
+                a: m(a),
+                b: m(b),
+                timing: *timing,
+            },
+            GameObject::Windmill { pivot, arm, timing } => GameObject::Windmill {
+                pivot: m(pivot),
+                arm: *arm,
+                timing: *timing,
+            },
+            GameObject::Teleporter { a, b, turn } => GameObject::Teleporter {
+                a: m(a),
+                b: m(b),
+                turn: *turn,
+            },
+            GameObject::Booster { pos, direction } => GameObject::Booster {
+                pos: m(pos),
+                direction: *direction,
+            },
+            GameObject::Conveyor { a, b, direction } => GameObject::Conveyor {
+                a: m(a),
+                b: m(b),
+                direction: *direction,
+            },
+            GameObject::Rail { a, b, thickness } => {
+                let m = |p: &Point| Point {
+                    x: p.x + dx * RAIL_STEPS,
+                    y: p.y + dy * RAIL_STEPS,
+                };
+                GameObject::Rail {
+                    a: m(a),
+                    b: m(b),
+                    thickness: *thickness,
+                }
+            }
+            GameObject::Bumper {
+                centre,
+                radius,
+                bounce,
+            } => GameObject::Bumper {
+                centre: Point {
+                    x: centre.x + dx * RAIL_STEPS,
+                    y: centre.y + dy * RAIL_STEPS,
+                },
+                radius: *radius,
+                bounce: *bounce,
+            },
+            GameObject::Arc {
+                centre,
+                radius,
+                from,
+                sweep,
+                thickness,
+            } => GameObject::Arc {
+                centre: Point {
+                    x: centre.x + dx * RAIL_STEPS,
+                    y: centre.y + dy * RAIL_STEPS,
+                },
+                radius: *radius,
+                from: *from,
+                sweep: *sweep,
+                thickness: *thickness,
+            },
+        }
+    }
+
+    /// The shape a rail, bumper or curved wall takes on a green with cells `cell_size` pixels across.
+    pub(crate) fn shape(&self, cell_size: f32) -> Option<Shape> {
+        let step = cell_size / RAIL_STEPS as f32;
+        let at = |p: &Point| Pos::new(p.x as f32 * step, p.y as f32 * step);
+        match self {
+            GameObject::Rail { a, b, thickness } => Some(Shape::Capsule {
+                a: at(a),
+                b: at(b),
+                radius: *thickness as f32 * step / 2.0,
+            }),
+            GameObject::Bumper { centre, radius, .. } => Some(Shape::Circle {
+                centre: at(centre),
+                radius: *radius as f32 * step,
+            }),
+            GameObject::Arc {
+                centre,
+                radius,
+                from,
+                sweep,
+                thickness,
+            } => Some(Shape::Arc {
+                centre: at(centre),
+                radius: *radius as f32 * step,
+                from: (*from as f32).to_radians(),
+                sweep: (*sweep as f32).to_radians(),
+                thickness: *thickness as f32 * step / 2.0,
+            }),
+            _ => None,
+        }
+    }
+
+    /// how much of its speed into the object the ball keeps when it bounces off, if the object sets that itself
+    pub(crate) fn bounce(&self) -> Option<f32> {
+        match self {
+            GameObject::Bumper { bounce, .. } => Some(*bounce as f32 / 100.0),
+            _ => None,
+        }
+    }
+}
+
+/// the cell a rail end is in, an end on the line between two cells counts as in the one above or to the left
+fn rail_cell(point: &Point) -> Point {
+    Point {
+        x: (point.x - 1).div_euclid(RAIL_STEPS).max(0),
+        y: (point.y - 1).div_euclid(RAIL_STEPS).max(0),
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(default)]
+pub(crate) struct Point {
+    pub(crate) x: i32,
+    pub(crate) y: i32,
+}
+
+impl Default for Point {
+    fn default() -> Self {
+        Self { x: 0, y: 0 }
+    }
+}
+
+impl Point {
+    fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub(crate) struct GolfMap {
+    pub(crate) objects: Vec<GameObject>,
+    pub(crate) name: String,
+    pub(crate) par: u32,
+    pub(crate) map: HashMap<Point, GameObject>,
+    /// share of its speed into a wall the ball keeps when it bounces off
+    pub(crate) restitution: f32,
+    /// size of the green in cells
+    pub(crate) width: i32,
+    pub(crate) height: i32,
+    /// size of a cell in pixels
+    pub(crate) cell_size: f32,
+    #[serde(skip)]
+    heightmap: RgbImage,
+    /// the ground in every cell that isn't grass, rebuilt along with `map`
+    #[serde(skip)]
+    surfaces: HashMap<Point, Surface>,
+    /// the way the conveyor in each cell runs, rebuilt along with `map`
+    #[serde(skip)]
+    conveyors: HashMap<Point, Direction>,
+}
+
+impl GolfMap {
+    pub(crate) fn get_point(&self, point: &Point) -> Option<&GameObject> {
+        self.map.get(point)
+    }
+
+    /// Index of the object at a cell. A start or hole wins over the area it sits in,
+    /// otherwise the last one added is picked since it is drawn on top.
+    pub(crate) fn object_at(&self, point: &Point) -> Option<usize> {
+        self.objects
+            .iter()
+            .rposition(|o| o.point() == Some(*point))
+            .or_else(|| self.objects.iter().rposition(|o| o.covers(point)))
+    }
+
+    /// The first wall (or edge of the green) that a ball moving from `start` along `motion` runs into,
+    /// and how much of its speed into the wall the ball keeps bouncing off.
+    /// Moving obstacles count as walls that stay where `moving` has them for the whole movement.
+    pub(crate) fn first_wall_hit(
+        &self,
+        start: Pos,
+        motion: Pos,
+        radius: f32,
+        moving: &[MovingShape],
+    ) -> Option<(Hit, f32)> {
+        let mut first: Option<(Hit, f32)> = None;
+        let mut check = |shape: Shape, restitution: f32| {
+            if let Some(hit) = shape.sweep(start, motion, radius) {
+                if first.map_or(true, |(f, _)| hit.time < f.time) {
+                    first = Some((hit, restitution));
+                }
+            }
+        };
+
+        for obj in &self.objects {
+            if let GameObject::Wall { a, b } = obj {
+                if a.x < 0 || a.y < 0 || b.x < 0 || b.y < 0 {
+                    // the placeholder for a wall that is still being placed
+                    continue;
+                }
+                let cell = self.cell_size;
+                check(
+                    Shape::Box {
+                        min: Pos::new(a.x.min(b.x) as f32 * cell, a.y.min(b.y) as f32 * cell),
+                        max: Pos::new(
+                            (a.x.max(b.x) + 1) as f32 * cell,
+                            (a.y.max(b.y) + 1) as f32 * cell,
+                        ),
+                    },
+                    self.restitution,
+                );
+            }
+            if let Some(shape) = obj.shape(self.cell_size) {
+                check(shape, obj.bounce().unwrap_or(self.restitution));
+            }
+        }
+
+        for obstacle in moving {
+            check(obstacle.shape, self.restitution);
+        }
+
+        // the edges of the green are walls that go on forever
+        let size = self.pixel_size();
+        let far = 10_000.0 + size.x + size.y;
+        check(
+            Shape::Box {
+                min: Pos::new(-far, -far),
+                max: Pos::new(0.0, far),
+            },
+            self.restitution,
+        );
+        check(
+            Shape::Box {
+                min: Pos::new(size.x, -far),
+                max: Pos::new(far, far),
+            },
+            self.restitution,
+        );
+        check(
+            Shape::Box {
+                min: Pos::new(-far, -far),
+                max: Pos::new(far, 0.0),
+            },
+            self.restitution,
+        );
+        check(
+            Shape::Box {
+                min: Pos::new(-far, size.y),
+                max: Pos::new(far, far),
+            },
+            self.restitution,
+        );
+
+        first
+    }
+
+    pub(crate) fn start(&self) -> Option<Point> {
+        self.objects.iter().find_map(|i| match i {
+            GameObject::Start(p) => Some(*p),
+            _ => None,
+        })
+    }
+
+    /// Where every moving obstacle is after `tick` physics steps, and how fast it is going.
+    pub(crate) fn moving_shapes(&self, tick: u32) -> Vec<MovingShape> {
+        self.objects
+            .iter()
+            .filter_map(|obj| match obj {
+                GameObject::SlidingBlock { a, b, timing } => {
+                    let (from, to) = (self.cell_centre(*a), self.cell_centre(*b));
+                    // there for the first half of the loop, back again for the second
+                    let fraction = timing.fraction(tick);
+                    let (along, direction) = if fraction < 0.5 {
+                        (fraction * 2.0, 1.0)
+                    } else {
+                        (2.0 - fraction * 2.0, -1.0)
+                    };
+                    let centre = from + (to - from) * along;
+                    let half = Pos::new(self.cell_size / 2.0, self.cell_size / 2.0);
+                    Some(MovingShape {
+                        shape: Shape::Box {
+                            min: centre - half,
+                            max: centre + half,
+                        },
+                        velocity: (to - from) * (2.0 * direction / timing.seconds()),
+                        spin: None,
+                    })
+                }
+                GameObject::Windmill { pivot, arm, timing } => {
+                    let centre = self.cell_centre(*pivot);
+                    let angle = std::f32::consts::TAU * timing.fraction(tick);
+                    let reach = Pos::new(angle.cos(), angle.sin()) * (*arm as f32 * self.cell_size);
+                    Some(MovingShape {
+                        shape: Shape::Capsule {
+                            a: centre - reach,
+                            b: centre + reach,
+                            radius: self.cell_size * SAIL_THICKNESS,
+                        },
+                        velocity: Pos::default(),
+                        spin: Some((centre, std::f32::consts::TAU / timing.seconds())),
+                    })
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub(crate) fn has_moving_obstacles(&self) -> bool {
+        self.objects.iter().any(|o| {
+            matches!(
+                o,
+                GameObject::SlidingBlock { .. } | GameObject::Windmill { .. }
+            )
+        })
+    }
+
+    /// size of the whole green in pixels
+    pub(crate) fn pixel_size(&self) -> Pos {
+        Pos::new(
+            self.width as f32 * self.cell_size,
+            self.height as f32 * self.cell_size,
+        )
+    }
+
+    pub(crate) fn contains(&self, point: &Point) -> bool {
+        (0..self.width).contains(&point.x) && (0..self.height).contains(&point.y)
+    }
+
+    /// the centre of a cell in pixels
+    pub(crate) fn cell_centre(&self, point: Point) -> Pos {
+        Pos::new(
+            (point.x as f32 + 0.5) * self.cell_size,
+            (point.y as f32 + 0.5) * self.cell_size,
+        )
+    }
+
+    /// the cell a position on the green is in
+    pub(crate) fn cell_at(&self, pos: Pos) -> Point {
+        Point {
+            x: (pos.x / self.cell_size).floor() as i32,
+            y: (pos.y / self.cell_size).floor() as i32,
+        }
+    }
+
+    /// Changes the size of the green, dropping anything that no longer fits on it.
+    pub(crate) fn resize(&mut self, width: i32, height: i32) {
+        self.width = width;
+        self.height = height;
+        let objects = std::mem::take(&mut self.objects);
+        self.objects = objects
+            .into_iter()
+            .filter(|o| o.get_points().iter().all(|p| self.contains(p)))
+            .collect();
+        self.update_hashmap();
+        self.update_heightmap();
+    }
+
+    pub(crate) fn hole(&self) -> Option<Point> {
+        self.objects.iter().find_map(|i| match i {
+            GameObject::Hole(p) => Some(*p),
+            _ => None,
+        })
+    }
+
+    pub(crate) fn update_heightmap(&mut self) {
+        let (w, h) = (self.width.max(1) as u32, self.height.max(1) as u32);
+        let mut image: RgbImage = RgbImage::from_pixel(w, h, Rgb([125, 125, 125]));
+
+        for i in &self.objects {
+            if let GameObject::Height { a, b, height } = i {
+                for i in a.x.min(b.x).max(0)..=b.x.max(a.x).min(w as i32 - 1) {
+                    for j in a.y.min(b.y).max(0)..=b.y.max(a.y).min(h as i32 - 1) {
+                        let og = image.get_pixel(i as u32, j as u32).0[0];
+
+                        image.put_pixel(
+                            i as u32,
+                            j as u32,
+                            Rgb([(og as i32 + *height).clamp(0, 255) as u8, 0, 0]),
+                        );
+                    }
+                }
+            }
+        }
+
+        let (w, h) = (w * HEIGHTMAP_CELL_PIXELS, h * HEIGHTMAP_CELL_PIXELS);
+        image = DynamicImage::ImageRgb8(image)
+            .resize_exact(w, h, FilterType::Triangle)
+            .to_rgb8();
+
+        for x in 0..w {
+            for y in 0..h {
+                let top = image.get_pixel(x, (y as i32 - 1).max(0) as u32);
+                let bottom = image.get_pixel(x, (y + 1).min(h - 1));
+                let left = image.get_pixel((x as i32 - 1).max(0) as u32, y);
+                let right = image.get_pixel((x + 1).min(w - 1), y);
+                let center = image.get_pixel(x, y);
+
+                let green = (top[0] as i32 - bottom[0] as i32 + 125).clamp(0, 255) as u8;
+                let blue = (left[0] as i32 - right[0] as i32 + 125).clamp(0, 255) as u8;
+
+                image.put_pixel(x, y, Rgb([center[0], green, blue]));
+            }
+        }
+
+        self.heightmap = image;
+    }
+
+    /// The downhill pull at a point on the green (in pixels), bilinearly interpolated
+    /// between the gradients stored in the green and blue channels of the heightmap.
+    pub(crate) fn slope(&self, pos: Pos) -> Pos {
+        let (w, h) = self.heightmap.dimensions();
+        if w == 0 || h == 0 {
+            return Pos::default();
+        }
+        // pixel centres sit half a pixel in from their corner
+        let scale = HEIGHTMAP_CELL_PIXELS as f32 / self.cell_size;
+        let x = (pos.x * scale - 0.5).clamp(0.0, (w - 1) as f32);
+        let y = (pos.y * scale - 0.5).clamp(0.0, (h - 1) as f32);
+        let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+        let (x1, y1) = ((x0 + 1).min(w - 1), (y0 + 1).min(h - 1));
+        let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+
+        let sample = |channel: usize| {
+            let p = |x, y| self.heightmap.get_pixel(x, y)[channel] as f32 - 125.0;
+            let top = p(x0, y0) * (1.0 - fx) + p(x1, y0) * fx;
+            let bottom = p(x0, y1) * (1.0 - fx) + p(x1, y1) * fx;
+            top * (1.0 - fy) + bottom * fy
+        };
+        // green holds the gradient along y and blue the gradient along x
+        Pos::new(sample(2), sample(1))
+    }
+
+    /// height of a cell relative to flat ground, used to shade the green
+    pub(crate) fn height_at(&self, point: &Point) -> i32 {
+        let x = ((point.x as f32 + 0.5) * HEIGHTMAP_CELL_PIXELS as f32) as u32;
+        let y = ((point.y as f32 + 0.5) * HEIGHTMAP_CELL_PIXELS as f32) as u32;
+        match self.heightmap.get_pixel_checked(x, y) {
+            Some(p) => p[0] as i32 - 125,
+            None => 0,
+        }
+    }
+
+    /// the ground in a cell, `None` for grass
+    pub(crate) fn surface_at(&self, point: &Point) -> Option<Surface> {
+        self.surfaces.get(point).copied()
+    }
+
+    /// the way a conveyor carries the ball in a cell, if there is one
+    pub(crate) fn conveyor_at(&self, point: &Point) -> Option<Direction> {
+        self.conveyors.get(point).copied()
+    }
+
+    pub(crate) fn update_hashmap(&mut self) {
+        self.map.clear();
+        self.surfaces.clear();
+        self.conveyors.clear();
+        for obj in &self.objects {
+            match obj {
+                GameObject::Teleporter { a, b, .. } => {
+                    self.map.insert(*a, *obj);
+                    self.map.insert(*b, *obj);
+                }
+                GameObject::Conveyor { a, b, direction } => {
+                    for i in a.x.min(b.x)..=b.x.max(a.x) {
+                        for j in a.y.min(b.y)..=b.y.max(a.y) {
+                            self.conveyors.insert(Point { x: i, y: j }, *direction);
+                        }
+                    }
+                }
+                // the last surface placed over a cell is the one it has
+                GameObject::Surface { a, b, surface } => {
+                    for i in a.x.min(b.x)..=b.x.max(a.x) {
+                        for j in a.y.min(b.y)..=b.y.max(a.y) {
+                            self.surfaces.insert(Point { x: i, y: j }, *surface);
+                        }
+                    }
+                }
+                GameObject::Wall { a, b } => {
+                    for i in a.x.min(b.x)..=b.x.max(a.x) {
+                        for j in a.y.min(b.y)..=b.y.max(a.y) {
+                            self.map.insert(Point { x: i, y: j }, *obj);
+                        }
+                    }
+                }
+                _ => {
+                    if let Some(p) = obj.point() {
+                        self.map.insert(p, *obj);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Default for GameObject {
+    fn default() -> Self {
+        Self::Start(Point::default())
+    }
+}
+
+impl Default for GolfMap {
+    fn default() -> Self {
+        Self {
+            objects: vec![GameObject::Start(Point::default())],
+            name: "New hole".to_owned(),
+            par: 3,
+            map: HashMap::new(),
+            restitution: 0.8,
+            width: 20,
+            height: 20,
+            cell_size: 20.0,
+            heightmap: RgbImage::from_pixel(200, 200, Rgb([125, 125, 125])),
+            surfaces: HashMap::new(),
+            conveyors: HashMap::new(),
+        }
+    }
+}
+
+/// an ordered list of holes that make up a round
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub(crate) struct Course {
+    pub(crate) name: String,
+    pub(crate) holes: Vec<GolfMap>,
+}
+
+impl Default for Course {
+    fn default() -> Self {
+        Self {
+            name: "My course".to_owned(),
+            holes: vec![GolfMap::default()],
+        }
+    }
+}
+
+impl Course {
+    pub(crate) fn total_par(&self) -> u32 {
+        self.holes.iter().map(|h| h.par).sum()
+    }
+}
@@ -0,0 +1,1118 @@
+use std::fmt;
+
+use strum::IntoEnumIterator;
+
+use crate::map::{
+    Direction, GameObject, GolfMap, Point, Surface, Timing, ARC_RADII, ARC_SWEEPS, ARM_LENGTHS,
+    BUMPER_BOUNCES, BUMPER_RADII, CELL_SIZES, GRID_SIZES, PERIODS, RAIL_STEPS, RAIL_THICKNESSES,
+};
+use crate::physics::{Pos, PHYSICS_DT};
+use crate::players::MAX_PLAYERS;
+use crate::replay::{Replay, Shot, MAX_SHOT_GAP};
+
+/// the characters a map code is written with, all of them are safe to put in a link
+const CHARS: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+/// written at the start of every code as `v3.`, codes without a version are from before versioning.
+/// 3 added the size, surface and obstacle records, so builds that only know 2 say these codes are too new
+const FORMAT_VERSION: u32 = 3;
+/// the oldest versioned format still read, version 2 codes are read just like version 3 ones
+const OLDEST_VERSION: u32 = 2;
+/// characters used for every number in the current format
+const VALUE_WIDTH: usize = 2;
+/// added to every number before it is written so negative numbers fit too
+const VALUE_OFFSET: i32 = 62 * 62 / 2;
+/// goes between the map code and the shots in a replay code
+const REPLAY_SEPARATOR: char = '~';
+/// characters used for ticks and speeds in a replay code, enough for any `u32` so nothing gets rounded
+const WIDE_WIDTH: usize = 6;
+
+/// Why a map code couldn't be read.
+///
+/// Positions count characters from the start of the code, starting at 0.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MapCodeError {
+    /// there is nothing to read
+    Empty,
+    /// the code was written by a newer (or unknown) version of the game
+    UnsupportedVersion { position: usize, version: String },
+    /// a character that is never part of a map code
+    InvalidCharacter { position: usize, found: char },
+    /// a character where a record should start that isn't a kind of record
+    UnknownRecord { position: usize, found: char },
+    /// the code stops part way through a record
+    Truncated { position: usize, record: char },
+    /// a number that is out of range for where it is used
+    InvalidValue {
+        position: usize,
+        reason: &'static str,
+    },
+    /// a replay code with only a map code in it
+    NoReplay { position: usize },
+}
+
+impl MapCodeError {
+    pub fn position(&self) -> usize {
+        match self {
+            MapCodeError::Empty => 0,
+            MapCodeError::UnsupportedVersion { position, .. }
+            | MapCodeError::InvalidCharacter { position, .. }
+            | MapCodeError::UnknownRecord { position, .. }
+            | MapCodeError::Truncated { position, .. }
+            | MapCodeError::InvalidValue { position, .. }
+            | MapCodeError::NoReplay { position } => *position,
+        }
+    }
+}
+
+impl fmt::Display for MapCodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // positions are shown counting from 1, the way people count characters
+        match self {
+            MapCodeError::Empty => write!(f, "the map code is empty"),
+            MapCodeError::UnsupportedVersion { position, version } => write!(
+                f,
+                "character {}: map code version {version} isn't supported, the newest this game reads is {FORMAT_VERSION}",
+                position + 1
+            ),
+            MapCodeError::InvalidCharacter { position, found } => {
+                write!(f, "character {}: '{found}' can't be part of a map code", position + 1)
+            }
+            MapCodeError::UnknownRecord { position, found } => {
+                write!(f, "character {}: '{found}' isn't a kind of map object", position + 1)
+            }
+            MapCodeError::Truncated { position, record } => {
+                write!(f, "character {}: the code ends part way through a '{record}' record", position + 1)
+            }
+            MapCodeError::InvalidValue { position, reason } => {
+                write!(f, "character {}: {reason}", position + 1)
+            }
+            MapCodeError::NoReplay { position } => {
+                write!(f, "character {}: a replay code needs a '{REPLAY_SEPARATOR}' and the shots after the map code", position + 1)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MapCodeError {}
+
+/// how many numbers follow each kind of record
+fn record_len(tag: char, legacy: bool) -> Option<usize> {
+    match tag {
+        'a' => Some(4),            // wall: a, b
+        'b' => Some(2),            // hole
+        'c' => Some(5),            // height: a, b, height
+        'd' => Some(2),            // start
+        'e' => Some(1),            // par
+        'f' if !legacy => Some(3), // width, height, cell size
+        'g' if !legacy => Some(5), // surface: a, b, kind
+        'h' if !legacy => Some(6), // sliding block: a, b, period, phase
+        'i' if !legacy => Some(5), // windmill: pivot, arm, period, phase
+        'j' if !legacy => Some(5), // teleporter: a, b, quarter turns
+        'k' if !legacy => Some(3), // booster: position, direction
+        'l' if !legacy => Some(5), // conveyor: a, b, direction
+        'm' if !legacy => Some(5), // rail: a, b in rail steps, thickness
+        'n' if !legacy => Some(4), // bumper: centre in rail steps, radius, bounce
+        'o' if !legacy => Some(6), // curved wall: centre in rail steps, radius, from, sweep, thickness
+        'r' if !legacy => Some(1), // wall bounce, in percent
+        _ => None,
+    }
+}
+
+fn encode_value(value: i32) -> String {
+    let n = (value.clamp(-VALUE_OFFSET, VALUE_OFFSET - 1) + VALUE_OFFSET) as usize;
+    let chars: Vec<char> = CHARS.chars().collect();
+    [chars[n / 62], chars[n % 62]].iter().collect()
+}
+
+fn encode_wide(value: u32) -> String {
+    let chars: Vec<char> = CHARS.chars().collect();
+    (0..WIDE_WIDTH as u32)
+        .rev()
+        .map(|i| chars[(value as u64 / 62u64.pow(i) % 62) as usize])
+        .collect()
+}
+
+fn char_value(c: char) -> Option<i32> {
+    CHARS.chars().position(|x| x == c).map(|i| i as i32)
+}
+
+struct Reader {
+    chars: Vec<char>,
+    pos: usize,
+    /// whitespace trimmed off the front, added back onto positions in errors
+    leading: usize,
+}
+
+impl Reader {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn position(&self) -> usize {
+        self.pos + self.leading
+    }
+
+    /// Reads the `v3.` header, the `v` has already been seen.
+    fn version(&mut self) -> Result<(), MapCodeError> {
+        let start = self.position();
+        self.pos += 1;
+        let mut version = String::new();
+        loop {
+            match self.peek() {
+                Some('.') => break,
+                Some(c) if c.is_ascii_digit() => version.push(c),
+                Some(found) => {
+                    return Err(MapCodeError::InvalidCharacter {
+                        position: self.position(),
+                        found,
+                    })
+                }
+                None => {
+                    return Err(MapCodeError::Truncated {
+                        position: self.position(),
+                        record: 'v',
+                    })
+                }
+            }
+            self.pos += 1;
+        }
+        self.pos += 1;
+        if !version
+            .parse()
+            .map_or(false, |v| (OLDEST_VERSION..=FORMAT_VERSION).contains(&v))
+        {
+            return Err(MapCodeError::UnsupportedVersion {
+                position: start,
+                version,
+            });
+        }
+        Ok(())
+    }
+
+    /// Reads one number `width` characters long, returning it along with where it started.
+    fn value(&mut self, width: usize, record: char) -> Result<(i32, usize), MapCodeError> {
+        let start = self.position();
+        let mut value = 0;
+        for _ in 0..width {
+            let Some(c) = self.peek() else {
+                return Err(MapCodeError::Truncated { position: self.position(), record });
+            };
+            let Some(digit) = char_value(c) else {
+                return Err(MapCodeError::InvalidCharacter { position: self.position(), found: c });
+            };
+            value = value * 62 + digit;
+            self.pos += 1;
+        }
+        // the original format only had one character per number and no negatives
+        if width > 1 {
+            value -= VALUE_OFFSET;
+        }
+        Ok((value, start))
+    }
+
+    /// Reads one of the long numbers used in replay codes, returning it along with where it started.
+    fn wide_value(&mut self, record: char) -> Result<(u32, usize), MapCodeError> {
+        let start = self.position();
+        let mut value: u64 = 0;
+        for _ in 0..WIDE_WIDTH {
+            let Some(c) = self.peek() else {
+                return Err(MapCodeError::Truncated { position: self.position(), record });
+            };
+            let Some(digit) = char_value(c) else {
+                return Err(MapCodeError::InvalidCharacter { position: self.position(), found: c });
+            };
+            value = value * 62 + digit as u64;
+            self.pos += 1;
+        }
+        let value = u32::try_from(value).map_err(|_| MapCodeError::InvalidValue {
+            position: start,
+            reason: "this number is too big",
+        })?;
+        Ok((value, start))
+    }
+
+    /// Reads a speed or position written out exactly, so a replayed shot goes just where it did the first time.
+    fn exact(&mut self, record: char) -> Result<f32, MapCodeError> {
+        let (bits, position) = self.wide_value(record)?;
+        Some(f32::from_bits(bits))
+            .filter(|v| v.is_finite())
+            .ok_or(MapCodeError::InvalidValue {
+                position,
+                reason: "shots have to be at a real place and speed",
+            })
+    }
+}
+
+fn direction_index(direction: &Direction) -> i32 {
+    Direction::iter().position(|d| d == *direction).unwrap_or(0) as i32
+}
+
+fn direction((index, position): (i32, usize)) -> Result<Direction, MapCodeError> {
+    usize::try_from(index)
+        .ok()
+        .and_then(|i| Direction::iter().nth(i))
+        .ok_or(MapCodeError::InvalidValue {
+            position,
+            reason: "directions go from 0 (right) to 7, clockwise",
+        })
+}
+
+/// Reads how long a moving obstacle takes to loop and where in the loop it starts.
+fn timing(
+    (period, period_position): (i32, usize),
+    (phase, phase_position): (i32, usize),
+) -> Result<Timing, MapCodeError> {
+    if !PERIODS.contains(&period) {
+        return Err(MapCodeError::InvalidValue {
+            position: period_position,
+            reason: "moving obstacles loop every 0.5 to 60 seconds",
+        });
+    }
+    if !(0..=99).contains(&phase) {
+        return Err(MapCodeError::InvalidValue {
+            position: phase_position,
+            reason: "phase goes from 0 to 99",
+        });
+    }
+    Ok(Timing { period, phase })
+}
+
+impl GolfMap {
+    /// Writes the map out as a short code of link safe characters that `from_text` reads back.
+    pub(crate) fn to_text(&self) -> String {
+        let mut s = format!("v{FORMAT_VERSION}.");
+        let mut record = |tag: char, values: &[i32]| {
+            s.push(tag);
+            for value in values {
+                s.push_str(&encode_value(*value));
+            }
+        };
+
+        record(
+            'f',
+            &[self.width, self.height, self.cell_size.round() as i32],
+        );
+        for i in &self.objects {
+            match i {
+                GameObject::Wall {
+                    a: Point { x: -1, y: -1 },
+                    b: Point { x: -1, y: -1 },
+                } => {}
+                GameObject::Wall { a, b } => record('a', &[a.x, a.y, b.x, b.y]),
+                GameObject::Hole(pos) => record('b', &[pos.x, pos.y]),
+                GameObject::Height { a, b, height } => record('c', &[a.x, a.y, b.x, b.y, *height]),
+                GameObject::Start(pos) => record('d', &[pos.x, pos.y]),
+                GameObject::Surface { a, b, surface } => {
+                    let kind = Surface::iter().position(|s| s == *surface).unwrap_or(0) as i32;
+                    record('g', &[a.x, a.y, b.x, b.y, kind]);
+                }
+                GameObject::SlidingBlock { a, b, timing } => {
+                    record('h', &[a.x, a.y, b.x, b.y, timing.period, timing.phase]);
+                }
+                GameObject::Windmill { pivot, arm, timing } => {
+                    record('i', &[pivot.x, pivot.y, *arm, timing.period, timing.phase]);
+                }
+                GameObject::Teleporter { a, b, turn } => record('j', &[a.x, a.y, b.x, b.y, *turn]),
+                GameObject::Booster { pos, direction } => {
+                    record('k', &[pos.x, pos.y, direction_index(direction)])
+                }
+                GameObject::Conveyor { a, b, direction } => {
+                    record('l', &[a.x, a.y, b.x, b.y, direction_index(direction)]);
+                }
+                GameObject::Rail { a, b, thickness } => {
+                    record('m', &[a.x, a.y, b.x, b.y, *thickness])
+                }
+                GameObject::Bumper {
+                    centre,
+                    radius,
+                    bounce,
+                } => record('n', &[centre.x, centre.y, *radius, *bounce]),
+                GameObject::Arc {
+                    centre,
+                    radius,
+                    from,
+                    sweep,
+                    thickness,
+                } => {
+                    record(
+                        'o',
+                        &[centre.x, centre.y, *radius, *from, *sweep, *thickness],
+                    );
+                }
+            }
+        }
+        record('e', &[self.par as i32]);
+        record('r', &[(self.restitution * 100.0).round() as i32]);
+        s
+    }
+
+    /// Reads a map code, either the current versioned format or the original one without a version.
+    /// Any problem with the code is an error, nothing is guessed at.
+    pub(crate) fn from_text(text: &str) -> Result<GolfMap, MapCodeError> {
+        let mut reader = Reader {
+            chars: text.trim().chars().collect(),
+            pos: 0,
+            leading: text.chars().take_while(|c| c.is_whitespace()).count(),
+        };
+        if reader.chars.is_empty() {
+            return Err(MapCodeError::Empty);
+        }
+        let legacy = reader.peek() != Some('v');
+        if !legacy {
+            reader.version()?;
+        }
+        let width = if legacy { 1 } else { VALUE_WIDTH };
+
+        let mut map = GolfMap::default();
+        map.objects.clear();
+        // points are checked against the size of the green once the whole code has been read
+        let mut points = Vec::new();
+        let mut rail_ends = Vec::new();
+        let mut point = |x: (i32, usize), y: (i32, usize)| {
+            points.push((x, y));
+            Point { x: x.0, y: y.0 }
+        };
+        // rail ends (and the middles of bumpers and curved walls) can sit on the far edge,
+        // so they go up to the size of the green in rail steps
+        let mut rail_end = |x: (i32, usize), y: (i32, usize)| {
+            rail_ends.push((x, y));
+            Point { x: x.0, y: y.0 }
+        };
+        while let Some(tag) = reader.peek() {
+            let position = reader.position();
+            let Some(len) = record_len(tag, legacy) else {
+                return Err(if char_value(tag).is_some() {
+                    MapCodeError::UnknownRecord { position, found: tag }
+                } else {
+                    MapCodeError::InvalidCharacter { position, found: tag }
+                });
+            };
+            reader.pos += 1;
+            let mut v = Vec::with_capacity(len);
+            for _ in 0..len {
+                v.push(reader.value(width, tag)?);
+            }
+
+            match tag {
+                'a' => map.objects.push(GameObject::Wall {
+                    a: point(v[0], v[1]),
+                    b: point(v[2], v[3]),
+                }),
+                'b' => map.objects.push(GameObject::Hole(point(v[0], v[1]))),
+                'c' => {
+                    let (height, position) = v[4];
+                    if !(-100..=100).contains(&height) {
+                        return Err(MapCodeError::InvalidValue {
+                            position,
+                            reason: "heights go from -100 to 100",
+                        });
+                    }
+                    map.objects.push(GameObject::Height {
+                        a: point(v[0], v[1]),
+                        b: point(v[2], v[3]),
+                        height,
+                    });
+                }
+                'd' => map.objects.push(GameObject::Start(point(v[0], v[1]))),
+                'g' => {
+                    let (kind, position) = v[4];
+                    let Some(surface) = usize::try_from(kind).ok().and_then(|k| Surface::iter().nth(k)) else {
+                        return Err(MapCodeError::InvalidValue { position, reason: "this isn't a kind of surface" });
+                    };
+                    map.objects.push(GameObject::Surface {
+                        a: point(v[0], v[1]),
+                        b: point(v[2], v[3]),
+                        surface,
+                    });
+                }
+                'h' => {
+                    let timing = timing(v[4], v[5])?;
+                    map.objects.push(GameObject::SlidingBlock {
+                        a: point(v[0], v[1]),
+                        b: point(v[2], v[3]),
+                        timing,
+                    });
+                }
+                'i' => {
+                    let (arm, position) = v[2];
+                    if !ARM_LENGTHS.contains(&arm) {
+                        return Err(MapCodeError::InvalidValue {
+                            position,
+                            reason: "windmills reach from 1 to 20 cells",
+                        });
+                    }
+                    let timing = timing(v[3], v[4])?;
+                    map.objects.push(GameObject::Windmill {
+                        pivot: point(v[0], v[1]),
+                        arm,
+                        timing,
+                    });
+                }
+                'j' => {
+                    let (turn, position) = v[4];
+                    if !(0..=3).contains(&turn) {
+                        return Err(MapCodeError::InvalidValue {
+                            position,
+                            reason: "teleporters turn the ball 0 to 3 quarter turns",
+                        });
+                    }
+                    map.objects.push(GameObject::Teleporter {
+                        a: point(v[0], v[1]),
+                        b: point(v[2], v[3]),
+                        turn,
+                    });
+                }
+                'k' => {
+                    let direction = direction(v[2])?;
+                    map.objects.push(GameObject::Booster {
+                        pos: point(v[0], v[1]),
+                        direction,
+                    });
+                }
+                'l' => {
+                    let direction = direction(v[4])?;
+                    map.objects.push(GameObject::Conveyor {
+                        a: point(v[0], v[1]),
+                        b: point(v[2], v[3]),
+                        direction,
+                    });
+                }
+                'm' => {
+                    let (thickness, position) = v[4];
+                    if !RAIL_THICKNESSES.contains(&thickness) {
+                        return Err(MapCodeError::InvalidValue {
+                            position,
+                            reason: "rails are from 1 to 10 tenths of a cell thick",
+                        });
+                    }
+                    map.objects.push(GameObject::Rail {
+                        a: rail_end(v[0], v[1]),
+                        b: rail_end(v[2], v[3]),
+                        thickness,
+                    });
+                }
+                'n' => {
+                    let (radius, position) = v[2];
+                    if !BUMPER_RADII.contains(&radius) {
+                        return Err(MapCodeError::InvalidValue {
+                            position,
+                            reason: "bumpers are from 2 to 30 tenths of a cell across their middle",
+                        });
+                    }
+                    let (bounce, position) = v[3];
+                    if !BUMPER_BOUNCES.contains(&bounce) {
+                        return Err(MapCodeError::InvalidValue {
+                            position,
+                            reason: "bumper bounce goes from 0 to 200",
+                        });
+                    }
+                    map.objects.push(GameObject::Bumper {
+                        centre: rail_end(v[0], v[1]),
+                        radius,
+                        bounce,
+                    });
+                }
+                'o' => {
+                    let (radius, position) = v[2];
+                    if !ARC_RADII.contains(&radius) {
+                        return Err(MapCodeError::InvalidValue {
+                            position,
+                            reason:
+                                "curved walls are from 1 to 1000 tenths of a cell from their middle",
+                        });
+                    }
+                    let (from, position) = v[3];
+                    if !(0..360).contains(&from) {
+                        return Err(MapCodeError::InvalidValue {
+                            position,
+                            reason: "curved walls start from 0 to 359 degrees round",
+                        });
+                    }
+                    let (sweep, position) = v[4];
+                    if !ARC_SWEEPS.contains(&sweep) {
+                        return Err(MapCodeError::InvalidValue {
+                            position,
+                            reason: "curved walls go from 1 to 360 degrees round",
+                        });
+                    }
+                    let (thickness, position) = v[5];
+                    if !RAIL_THICKNESSES.contains(&thickness) {
+                        return Err(MapCodeError::InvalidValue {
+                            position,
+                            reason: "curved walls are from 1 to 10 tenths of a cell thick",
+                        });
+                    }
+                    map.objects.push(GameObject::Arc {
+                        centre: rail_end(v[0], v[1]),
+                        radius,
+                        from,
+                        sweep,
+                        thickness,
+                    });
+                }
+                'e' => {
+                    let (par, position) = v[0];
+                    if !(1..=20).contains(&par) {
+                        return Err(MapCodeError::InvalidValue {
+                            position,
+                            reason: "par goes from 1 to 20",
+                        });
+                    }
+                    map.par = par as u32;
+                }
+                'r' => {
+                    let (percent, position) = v[0];
+                    if !(0..=100).contains(&percent) {
+                        return Err(MapCodeError::InvalidValue {
+                            position,
+                            reason: "wall bounce goes from 0 to 100",
+                        });
+                    }
+                    map.restitution = percent as f32 / 100.0;
+                }
+                'f' => {
+                    for (size, position) in [v[0], v[1]] {
+                        if !GRID_SIZES.contains(&size) {
+                            return Err(MapCodeError::InvalidValue {
+                                position,
+                                reason: "the green must be from 2 to 100 cells on each side",
+                            });
+                        }
+                    }
+                    let (cell_size, position) = v[2];
+                    if !CELL_SIZES.contains(&(cell_size as f32)) {
+                        return Err(MapCodeError::InvalidValue {
+                            position,
+                            reason: "cells must be from 4 to 64 pixels across",
+                        });
+                    }
+                    map.width = v[0].0;
+                    map.height = v[1].0;
+                    map.cell_size = cell_size as f32;
+                }
+                _ => unreachable!("record_len only knows the tags handled here"),
+            }
+        }
+
+        for ((x, x_position), (y, y_position)) in points {
+            for (value, position, size) in [(x, x_position, map.width), (y, y_position, map.height)]
+            {
+                if !(0..size).contains(&value) {
+                    return Err(MapCodeError::InvalidValue {
+                        position,
+                        reason: "this point is off the edge of the green",
+                    });
+                }
+            }
+        }
+        for ((x, x_position), (y, y_position)) in rail_ends {
+            for (value, position, size) in [(x, x_position, map.width), (y, y_position, map.height)]
+            {
+                if !(0..=size * RAIL_STEPS).contains(&value) {
+                    return Err(MapCodeError::InvalidValue {
+                        position,
+                        reason: "this rail end is off the edge of the green",
+                    });
+                }
+            }
+        }
+
+        map.update_hashmap();
+        map.update_heightmap();
+        Ok(map)
+    }
+}
+
+impl Replay {
+    /// Writes the replay out as its map code, a `~`, then the shots, all link safe like a map code.
+    pub(crate) fn to_text(&self) -> String {
+        let mut s = format!(
+            "{}{REPLAY_SEPARATOR}p{}{}",
+            self.code,
+            encode_value(self.players as i32),
+            encode_value(self.balls_collide as i32)
+        );
+        for shot in &self.shots {
+            s += "s";
+            s += &encode_value(shot.player as i32);
+            s += &encode_wide(shot.tick);
+            for value in [shot.start.x, shot.start.y, shot.velocity.x, shot.velocity.y] {
+                s += &encode_wide(value.to_bits());
+            }
+        }
+        s
+    }
+
+    /// Reads a replay code written by `to_text`, checking the map code in it as well.
+    pub(crate) fn from_text(text: &str) -> Result<Replay, MapCodeError> {
+        let text = text.trim_end();
+        let Some((code, shots)) = text.split_once(REPLAY_SEPARATOR) else {
+            return Err(MapCodeError::NoReplay { position: text.chars().count() });
+        };
+        let map = GolfMap::from_text(code)?;
+        let mut reader = Reader {
+            chars: shots.chars().collect(),
+            pos: 0,
+            leading: code.chars().count() + 1,
+        };
+        let mut replay = Replay::new(map.to_text(), 1, true);
+        // players are checked against how many there were once the whole code has been read
+        let mut shot_players = Vec::new();
+        while let Some(tag) = reader.peek() {
+            let position = reader.position();
+            reader.pos += 1;
+            match tag {
+                'p' => {
+                    let (players, position) = reader.value(VALUE_WIDTH, tag)?;
+                    if !(1..=MAX_PLAYERS as i32).contains(&players) {
+                        return Err(MapCodeError::InvalidValue {
+                            position,
+                            reason: "replays have from 1 to 6 players",
+                        });
+                    }
+                    let (collide, position) = reader.value(VALUE_WIDTH, tag)?;
+                    if !(0..=1).contains(&collide) {
+                        return Err(MapCodeError::InvalidValue {
+                            position,
+                            reason: "balls knocking each other is 0 (off) or 1 (on)",
+                        });
+                    }
+                    replay.players = players as usize;
+                    replay.balls_collide = collide == 1;
+                }
+                's' => {
+                    let player = reader.value(VALUE_WIDTH, tag)?;
+                    let (tick, tick_position) = reader.wide_value(tag)?;
+                    let previous = replay.shots.last().map_or(0, |s| s.tick);
+                    if previous > tick {
+                        return Err(MapCodeError::InvalidValue {
+                            position: tick_position,
+                            reason: "shots have to be in the order they were taken",
+                        });
+                    }
+                    if tick - previous > (MAX_SHOT_GAP / PHYSICS_DT) as u32 {
+                        return Err(MapCodeError::InvalidValue {
+                            position: tick_position,
+                            reason: "shots can't be more than 10 minutes apart",
+                        });
+                    }
+                    let start = Pos {
+                        x: reader.exact(tag)?,
+                        y: reader.exact(tag)?,
+                    };
+                    let velocity = Pos {
+                        x: reader.exact(tag)?,
+                        y: reader.exact(tag)?,
+                    };
+                    shot_players.push(player);
+                    replay.shots.push(Shot {
+                        player: player.0.max(0) as usize,
+                        tick,
+                        start,
+                        velocity,
+                    });
+                }
+                found if char_value(found).is_some() => {
+                    return Err(MapCodeError::UnknownRecord { position, found })
+                }
+                found => return Err(MapCodeError::InvalidCharacter { position, found }),
+            }
+        }
+
+        for (player, position) in shot_players {
+            if !(0..replay.players as i32).contains(&player) {
+                return Err(MapCodeError::InvalidValue {
+                    position,
+                    reason: "this shot was taken by a player who isn't playing",
+                });
+            }
+        }
+        Ok(replay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn p(x: i32, y: i32) -> Point {
+        Point { x, y }
+    }
+
+    /// a map code with `records` after the version, so positions in it are easy to count
+    fn current(records: &str) -> String {
+        format!("v{FORMAT_VERSION}.{records}")
+    }
+
+    fn error(text: &str) -> MapCodeError {
+        GolfMap::from_text(text)
+            .err()
+            .expect("the code should not be read")
+    }
+
+    #[test]
+    fn every_kind_of_record_round_trips() {
+        let timing = Timing {
+            period: 35,
+            phase: 40,
+        };
+        let mut map = GolfMap::default();
+        map.objects = vec![
+            GameObject::Start(p(1, 2)),
+            GameObject::Hole(p(20, 21)),
+            GameObject::Wall {
+                a: p(0, 0),
+                b: p(5, 0),
+            },
+            GameObject::Height {
+                a: p(2, 2),
+                b: p(4, 6),
+                height: 75,
+            },
+            GameObject::Surface {
+                a: p(7, 1),
+                b: p(9, 3),
+                surface: Surface::Water,
+            },
+            GameObject::SlidingBlock {
+                a: p(3, 10),
+                b: p(8, 10),
+                timing,
+            },
+            GameObject::Windmill {
+                pivot: p(12, 12),
+                arm: 3,
+                timing,
+            },
+            GameObject::Teleporter {
+                a: p(1, 20),
+                b: p(20, 1),
+                turn: 3,
+            },
+            GameObject::Booster {
+                pos: p(6, 6),
+                direction: Direction::UpLeft,
+            },
+            GameObject::Conveyor {
+                a: p(14, 2),
+                b: p(16, 8),
+                direction: Direction::Down,
+            },
+            GameObject::Rail {
+                a: p(0, 0),
+                b: p(220, 230),
+                thickness: 4,
+            },
+            GameObject::Bumper {
+                centre: p(105, 95),
+                radius: 12,
+                bounce: 150,
+            },
+            GameObject::Arc {
+                centre: p(150, 50),
+                radius: 30,
+                from: 270,
+                sweep: 90,
+                thickness: 2,
+            },
+        ];
+        map.width = 22;
+        map.height = 23;
+        map.cell_size = 32.0;
+        map.par = 7;
+        map.restitution = 0.45;
+
+        let read = GolfMap::from_text(&map.to_text()).unwrap();
+        assert_eq!(read.objects, map.objects);
+        assert_eq!((read.width, read.height, read.cell_size), (22, 23, 32.0));
+        assert_eq!(read.par, 7);
+        assert_eq!(read.restitution, 0.45);
+        assert_eq!(read.to_text(), map.to_text());
+    }
+
+    #[test]
+    fn negative_heights_round_trip() {
+        let mut map = GolfMap::default();
+        map.objects = vec![
+            GameObject::Height {
+                a: p(0, 0),
+                b: p(3, 3),
+                height: -100,
+            },
+            GameObject::Height {
+                a: p(5, 5),
+                b: p(6, 6),
+                height: -1,
+            },
+        ];
+        assert_eq!(
+            GolfMap::from_text(&map.to_text()).unwrap().objects,
+            map.objects
+        );
+    }
+
+    #[test]
+    fn legacy_codes_have_one_character_values() {
+        // wall from (0, 0) to (5, 5), hole at (10, 10), height 20 over (1, 1) to (2, 2), start at (1, 10), par 4
+        let map = GolfMap::from_text("aaaffbkkcbbccudbkee").unwrap();
+        assert_eq!(
+            map.objects,
+            vec![
+                GameObject::Wall {
+                    a: p(0, 0),
+                    b: p(5, 5)
+                },
+                GameObject::Hole(p(10, 10)),
+                GameObject::Height {
+                    a: p(1, 1),
+                    b: p(2, 2),
+                    height: 20
+                },
+                GameObject::Start(p(1, 10)),
+            ]
+        );
+        assert_eq!(map.par, 4);
+        assert_eq!((map.width, map.height), (20, 20));
+    }
+
+    #[test]
+    fn legacy_records_can_come_in_any_order() {
+        let map = GolfMap::from_text("eddbkbkkaaaff").unwrap();
+        assert_eq!(
+            map.objects,
+            vec![
+                GameObject::Start(p(1, 10)),
+                GameObject::Hole(p(10, 10)),
+                GameObject::Wall {
+                    a: p(0, 0),
+                    b: p(5, 5)
+                }
+            ]
+        );
+        assert_eq!(map.par, 3);
+    }
+
+    #[test]
+    fn empty_code() {
+        assert_eq!(error(""), MapCodeError::Empty);
+        assert_eq!(error("  \n"), MapCodeError::Empty);
+    }
+
+    #[test]
+    fn version_2_codes_still_read() {
+        let code = format!(
+            "v2.d{}{}b{}{}",
+            encode_value(1),
+            encode_value(10),
+            encode_value(18),
+            encode_value(10)
+        );
+        let map = GolfMap::from_text(&code).unwrap();
+        assert_eq!(
+            map.objects,
+            vec![GameObject::Start(p(1, 10)), GameObject::Hole(p(18, 10))]
+        );
+        assert!(map.to_text().starts_with("v3."));
+        assert_eq!(error("v1.").position(), 0);
+    }
+
+    #[test]
+    fn unsupported_version() {
+        let found = error("  v9.fababab");
+        assert_eq!(
+            found,
+            MapCodeError::UnsupportedVersion {
+                position: 2,
+                version: "9".to_owned()
+            }
+        );
+        assert_eq!(found.position(), 2);
+    }
+
+    #[test]
+    fn invalid_character() {
+        assert_eq!(error("v2x.").position(), 2);
+        assert_eq!(
+            error(&current("fa!")),
+            MapCodeError::InvalidCharacter {
+                position: 5,
+                found: '!'
+            }
+        );
+        assert_eq!(
+            error(&current("-")),
+            MapCodeError::InvalidCharacter {
+                position: 3,
+                found: '-'
+            }
+        );
+    }
+
+    #[test]
+    fn unknown_record() {
+        assert_eq!(
+            error(&current("z")),
+            MapCodeError::UnknownRecord {
+                position: 3,
+                found: 'z'
+            }
+        );
+        // sizes weren't in the original format
+        assert_eq!(
+            error("dbkf"),
+            MapCodeError::UnknownRecord {
+                position: 3,
+                found: 'f'
+            }
+        );
+    }
+
+    #[test]
+    fn truncated() {
+        assert_eq!(
+            error("v2"),
+            MapCodeError::Truncated {
+                position: 2,
+                record: 'v'
+            }
+        );
+        let found = error(&current(&format!("b{}a", encode_value(3))));
+        assert_eq!(
+            found,
+            MapCodeError::Truncated {
+                position: 7,
+                record: 'b'
+            }
+        );
+        assert_eq!(found.position(), 7);
+    }
+
+    #[test]
+    fn invalid_value() {
+        let height = format!(
+            "c{}{}{}{}{}",
+            encode_value(0),
+            encode_value(0),
+            encode_value(1),
+            encode_value(1),
+            encode_value(101)
+        );
+        let found = error(&current(&height));
+        assert_eq!(
+            found,
+            MapCodeError::InvalidValue {
+                position: 12,
+                reason: "heights go from -100 to 100"
+            }
+        );
+        assert_eq!(found.position(), 12);
+
+        // points are checked once the size is known, even if the size comes after them
+        let hole = format!(
+            "b{}{}f{}{}{}",
+            encode_value(3),
+            encode_value(30),
+            encode_value(40),
+            encode_value(20),
+            encode_value(20)
+        );
+        assert_eq!(
+            error(&current(&hole)),
+            MapCodeError::InvalidValue {
+                position: 6,
+                reason: "this point is off the edge of the green"
+            }
+        );
+    }
+
+    fn replay() -> Replay {
+        let mut map = GolfMap::default();
+        map.objects = vec![GameObject::Start(p(1, 10)), GameObject::Hole(p(10, 10))];
+        let shot = |player, tick, x| Shot {
+            player,
+            tick,
+            start: Pos::new(x, 210.0),
+            velocity: Pos::new(123.456, -0.1),
+        };
+        Replay {
+            code: map.to_text(),
+            players: 2,
+            balls_collide: true,
+            shots: vec![shot(0, 0, 30.0), shot(1, 1000, 30.5)],
+        }
+    }
+
+    /// where the `n`th shot of a replay code starts
+    fn shot_position(replay: &Replay, n: usize) -> usize {
+        replay.code.len() + 1 + 1 + 2 * VALUE_WIDTH + n * (1 + VALUE_WIDTH + 5 * WIDE_WIDTH)
+    }
+
+    #[test]
+    fn replays_round_trip_exactly() {
+        let replay = replay();
+        assert_eq!(Replay::from_text(&replay.to_text()).unwrap(), replay);
+    }
+
+    #[test]
+    fn replay_without_shots() {
+        let code = replay().code;
+        assert_eq!(
+            Replay::from_text(&code),
+            Err(MapCodeError::NoReplay {
+                position: code.len()
+            })
+        );
+    }
+
+    #[test]
+    fn replay_shots_out_of_order() {
+        let mut replay = replay();
+        replay.shots[1].tick = 0;
+        replay.shots[0].tick = 5;
+        let tick = shot_position(&replay, 1) + 1 + VALUE_WIDTH;
+        assert_eq!(
+            Replay::from_text(&replay.to_text()),
+            Err(MapCodeError::InvalidValue {
+                position: tick,
+                reason: "shots have to be in the order they were taken"
+            })
+        );
+    }
+
+    #[test]
+    fn replay_shots_too_far_apart() {
+        let mut replay = replay();
+        replay.shots[1].tick = replay.shots[0].tick + (MAX_SHOT_GAP / PHYSICS_DT) as u32 + 1;
+        let tick = shot_position(&replay, 1) + 1 + VALUE_WIDTH;
+        assert_eq!(
+            Replay::from_text(&replay.to_text()),
+            Err(MapCodeError::InvalidValue {
+                position: tick,
+                reason: "shots can't be more than 10 minutes apart"
+            })
+        );
+
+        // waiting that long before the first shot is just as bad
+        let mut waited = replay;
+        waited.shots[0].tick = u32::MAX / 2;
+        waited.shots[1].tick = u32::MAX / 2;
+        assert_eq!(
+            Replay::from_text(&waited.to_text()).map_err(|e| e.position()),
+            Err(shot_position(&waited, 0) + 1 + VALUE_WIDTH)
+        );
+    }
+
+    #[test]
+    fn replay_shot_by_unknown_player() {
+        let mut replay = replay();
+        replay.shots[1].player = 2;
+        let player = shot_position(&replay, 1) + 1;
+        assert_eq!(
+            Replay::from_text(&replay.to_text()),
+            Err(MapCodeError::InvalidValue {
+                position: player,
+                reason: "this shot was taken by a player who isn't playing"
+            })
+        );
+    }
+}
@@ -0,0 +1,951 @@
+use std::ops::{Add, AddAssign, Mul, Sub};
+
+use crate::map::{GameObject, GolfMap, Point, Surface};
+
+/// radius of the cup in pixels, the red ring around the hole is drawn this size
+pub(crate) const CUP_RADIUS: f32 = 10.0;
+/// fastest the ball can cross the middle of the cup and still drop in (pixels per second)
+const CAPTURE_SPEED: f32 = 250.0;
+/// how hard the rim of the cup bends the path of a ball that is going too fast to drop
+const LIP_PULL: f32 = 3000.0;
+/// length of one physics step in seconds, the simulation always advances in steps of exactly this size
+pub(crate) const PHYSICS_DT: f32 = 1.0 / 120.0;
+/// furthest from the ball the pointer can be to aim a shot (pixels)
+pub(crate) const AIM_REACH: f32 = 80.0;
+/// speed the ball is hit with for each pixel the pointer is pulled back from it
+pub(crate) const AIM_STRENGTH: f32 = 12.0;
+/// fraction of its speed the ball keeps after rolling on grass for one second
+const FRICTION_PER_SECOND: f32 = 0.3;
+/// the same for each kind of surface
+const SAND_FRICTION_PER_SECOND: f32 = 0.01;
+const ROUGH_FRICTION_PER_SECOND: f32 = 0.08;
+const ICE_FRICTION_PER_SECOND: f32 = 0.85;
+/// radius of the ball in pixels
+const BALL_RADIUS: f32 = 8.0;
+/// how much of the speed they meet with two balls keep bouncing off each other
+const BALL_RESTITUTION: f32 = 0.9;
+/// gap left between the ball and a wall after a bounce, so it doesn't start the next step touching it
+const CONTACT_SKIN: f32 = 0.01;
+/// most wall bounces handled in one physics step, enough to get out of any corner
+const MAX_BOUNCES_PER_STEP: usize = 4;
+/// below this speed the ball is considered to have stopped (pixels per second)
+const REST_SPEED: f32 = 1.0;
+/// how quickly a slope speeds the ball up, per unit of heightmap gradient (pixels per second squared)
+const SLOPE_ACCEL: f32 = 150.0;
+/// speed a booster adds to the ball (pixels per second)
+const BOOST_SPEED: f32 = 400.0;
+/// how quickly a conveyor speeds the ball up along it (pixels per second squared)
+const CONVEYOR_ACCEL: f32 = 250.0;
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub(crate) struct GolfBall {
+    pub(crate) pos: Pos,
+    pub(crate) vel: Pos,
+    shoot: bool,
+    pub(crate) sunk: bool,
+    /// where the ball last stopped, it goes back here out of the water
+    pub(crate) last_rest: Pos,
+}
+
+impl Default for GolfBall {
+    fn default() -> Self {
+        Self {
+            pos: Pos::default(),
+            vel: Pos::default(),
+            shoot: false,
+            sunk: false,
+            last_rest: Pos::default(),
+        }
+    }
+}
+
+/// What happened to the ball during one physics step.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct StepEvents {
+    /// how many times the ball bounced off a wall
+    pub(crate) wall_hits: u32,
+    /// the ball rolled into water and was put back where it last stopped, which costs a stroke
+    pub(crate) splashed: bool,
+}
+
+/// fraction of its speed the ball keeps after rolling over some ground for a second
+fn friction_per_second(surface: Option<Surface>) -> f32 {
+    match surface {
+        Some(Surface::Sand) => SAND_FRICTION_PER_SECOND,
+        Some(Surface::Rough) => ROUGH_FRICTION_PER_SECOND,
+        Some(Surface::Ice) => ICE_FRICTION_PER_SECOND,
+        // the ball never stays in the water long enough to roll
+        Some(Surface::Water) | None => FRICTION_PER_SECOND,
+    }
+}
+
+impl Add for Pos {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self {
+            x: self.x + rhs.x,
+            y: self.y + rhs.y,
+        }
+    }
+}
+
+impl AddAssign for Pos {
+    fn add_assign(&mut self, rhs: Self) {
+        self.x += rhs.x;
+        self.y += rhs.y;
+    }
+}
+
+impl GolfBall {
+    /// Puts the ball down at rest, somewhere it can be put back to from the water.
+    pub(crate) fn place(&mut self, pos: Pos) {
+        self.pos = pos;
+        self.last_rest = pos;
+        self.vel = Pos::default();
+        self.sunk = false;
+    }
+
+    /// Advances the ball by one fixed physics step of `PHYSICS_DT`.
+    /// `tick` counts the steps since the hole started, it is what moves the moving obstacles.
+    pub(crate) fn update_pos(&mut self, map: &GolfMap, tick: u32) -> StepEvents {
+        if self.sunk {
+            return StepEvents::default();
+        }
+        // obstacles are placed where they are at the end of the step
+        let moving = map.moving_shapes(tick.wrapping_add(1));
+        let pushes = self.get_pushed(map, &moving);
+        let delta = PHYSICS_DT;
+        // roll downhill
+        let slope = map.slope(self.pos);
+        self.vel.x += slope.x * SLOPE_ACCEL * delta;
+        self.vel.y += slope.y * SLOPE_ACCEL * delta;
+
+        // get carried along by conveyors
+        if let Some(direction) = map.conveyor_at(&map.cell_at(self.pos)) {
+            self.vel += direction.vector() * (CONVEYOR_ACCEL * delta);
+        }
+
+        let surface = map.surface_at(&map.cell_at(self.pos));
+        let friction = friction_per_second(surface).powf(delta);
+        self.vel.x *= friction;
+        self.vel.y *= friction;
+
+        let from = map.cell_at(self.pos);
+        let mut events = StepEvents {
+            wall_hits: pushes + self.move_and_collide(map, &moving, delta),
+            ..StepEvents::default()
+        };
+        self.boost(map, from);
+        self.teleport(map, from);
+
+        self.check_hole(map, delta);
+
+        if !self.sunk && map.surface_at(&map.cell_at(self.pos)) == Some(Surface::Water) {
+            self.place(self.last_rest);
+            events.splashed = true;
+        }
+
+        if self.vel.velocity() < REST_SPEED {
+            self.vel = Pos::default();
+            self.last_rest = self.pos;
+        }
+        events
+    }
+
+    /// Moves the ball along its velocity for `delta` seconds, stopping at the first wall in
+    /// the way, bouncing off it and carrying on with whatever time is left.
+    /// Because the whole path is swept the ball can't skip through a wall however fast it goes.
+    fn move_and_collide(&mut self, map: &GolfMap, moving: &[MovingShape], delta: f32) -> u32 {
+        let mut time_left = delta;
+        let mut hits = 0;
+        for _ in 0..MAX_BOUNCES_PER_STEP {
+            let motion = self.vel * time_left;
+            let Some((hit, restitution)) = map.first_wall_hit(self.pos, motion, BALL_RADIUS, moving) else {
+                self.pos += motion;
+                return hits;
+            };
+            // stop at the point of contact, nudged off the surface so the next sweep starts outside it
+            self.pos += motion * hit.time + hit.normal * CONTACT_SKIN;
+            time_left *= 1.0 - hit.time;
+            self.vel = self.vel.reflect(hit.normal, restitution);
+            hits += 1;
+        }
+        hits
+    }
+
+    /// Kicks the ball along a booster's arrow when it rolls onto one from the cell `from`.
+    fn boost(&mut self, map: &GolfMap, from: Point) {
+        let cell = map.cell_at(self.pos);
+        if cell == from {
+            return;
+        }
+        if let Some(GameObject::Booster { direction, .. }) = map.get_point(&cell) {
+            self.vel += direction.vector() * BOOST_SPEED;
+        }
+    }
+
+    /// Sends the ball out of the other pad of a teleporter when it rolls onto one from the cell `from`.
+    /// Coming out of a pad doesn't count as rolling onto it, so the ball doesn't bounce straight back.
+    fn teleport(&mut self, map: &GolfMap, from: Point) {
+        let cell = map.cell_at(self.pos);
+        if cell == from {
+            return;
+        }
+        let Some(GameObject::Teleporter { a, b, turn }) = map.get_point(&cell) else {
+            return;
+        };
+        let exit = if cell == *a { *b } else { *a };
+        self.pos = map.cell_centre(exit);
+        for _ in 0..turn.rem_euclid(4) {
+            // a quarter turn clockwise, on a screen where y points down
+            self.vel = Pos::new(-self.vel.y, self.vel.x);
+        }
+    }
+
+    /// Shoves the ball out of any moving obstacle that has run into it since the last step,
+    /// sending it off with the obstacle's speed as well as its own.
+    fn get_pushed(&mut self, map: &GolfMap, moving: &[MovingShape]) -> u32 {
+        let mut hits = 0;
+        for obstacle in moving {
+            let Some((normal, depth)) = obstacle.shape.penetration(self.pos, BALL_RADIUS) else {
+                continue;
+            };
+            self.pos += normal * (depth + CONTACT_SKIN);
+            let carried = obstacle.velocity_at(self.pos);
+            let relative = self.vel - carried;
+            if relative.dot(normal) < 0.0 {
+                self.vel = carried + relative.reflect(normal, map.restitution);
+                hits += 1;
+            }
+        }
+        hits
+    }
+
+    /// Bounces two balls that are touching off each other, returning whether they hit.
+    /// Balls in the cup are out of the way.
+    pub(crate) fn collide(&mut self, other: &mut GolfBall) -> bool {
+        if self.sunk || other.sunk {
+            return false;
+        }
+        let offset = other.pos - self.pos;
+        let distance = offset.velocity();
+        if distance >= BALL_RADIUS * 2.0 {
+            return false;
+        }
+        let normal = if distance > 0.0 {
+            offset * (1.0 / distance)
+        } else {
+            Pos::new(1.0, 0.0)
+        };
+        // move them apart evenly so they are just touching
+        let apart = (BALL_RADIUS * 2.0 - distance) / 2.0 + CONTACT_SKIN;
+        self.pos += normal * -apart;
+        other.pos += normal * apart;
+        let closing = (self.vel - other.vel).dot(normal);
+        if closing <= 0.0 {
+            return false;
+        }
+        // balls the same weight trade the part of their speed along the line between them
+        let impulse = normal * (closing * (1.0 + BALL_RESTITUTION) / 2.0);
+        self.vel += impulse * -1.0;
+        other.vel += impulse;
+        true
+    }
+
+    pub(crate) fn at_rest(&self) -> bool {
+        self.sunk || self.vel.velocity() < REST_SPEED
+    }
+
+    /// Drops the ball into the cup when it is close and slow enough.
+    /// A ball that is going too fast gets its path bent by the rim instead (a lip-out).
+    fn check_hole(&mut self, map: &GolfMap, delta: f32) {
+        let Some(hole) = map.hole() else {
+            return;
+        };
+        let centre = map.cell_centre(hole);
+        let offset = Pos::new(centre.x - self.pos.x, centre.y - self.pos.y);
+        let distance = offset.velocity();
+        if distance > CUP_RADIUS {
+            return;
+        }
+
+        // a ball rolling over the middle of the cup can be going faster than one that only catches the edge
+        let capture_speed = CAPTURE_SPEED * (1.0 - 0.5 * distance / CUP_RADIUS);
+        if self.vel.velocity() <= capture_speed {
+            self.sunk = true;
+            self.pos = centre;
+            self.vel = Pos::default();
+        } else if distance > 0.0 {
+            self.vel.x += offset.x / distance * LIP_PULL * delta;
+            self.vel.y += offset.y / distance * LIP_PULL * delta;
+        }
+    }
+}
+
+/// A position or velocity on the green, in pixels (per second) from the top left corner.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct Pos {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Pos {
+    // impl into
+    pub fn new<T: Into<f32>>(x: T, y: T) -> Self {
+        Self {
+            x: x.into(),
+            y: y.into(),
+        }
+    }
+
+    pub(crate) fn velocity(&self) -> f32 {
+        (self.x.powi(2) + self.y.powi(2)).sqrt()
+    }
+
+    pub(crate) fn dot(&self, other: Pos) -> f32 {
+        self.x * other.x + self.y * other.y
+    }
+
+    /// Bounces off a surface with the unit `normal`, keeping `restitution` of the speed into it.
+    fn reflect(&self, normal: Pos, restitution: f32) -> Pos {
+        let into = self.dot(normal);
+        if into >= 0.0 {
+            return *self;
+        }
+        *self - normal * ((1.0 + restitution) * into)
+    }
+}
+
+impl Sub for Pos {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self {
+            x: self.x - rhs.x,
+            y: self.y - rhs.y,
+        }
+    }
+}
+
+impl Mul<f32> for Pos {
+    type Output = Self;
+
+    fn mul(self, rhs: f32) -> Self::Output {
+        Self {
+            x: self.x * rhs,
+            y: self.y * rhs,
+        }
+    }
+}
+
+/// where and how a moving circle first touches something
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Hit {
+    /// fraction of the movement completed before contact, from 0 to 1
+    pub(crate) time: f32,
+    /// unit surface normal at the point of contact, pointing back towards the circle
+    normal: Pos,
+}
+
+/// Sweeps a circle from `start` along `motion` against the box `min..max`.
+///
+/// This is a ray cast against the box grown by `radius` with rounded corners, so hits
+/// on the faces and on the corners both get the right normal. Touching a surface while
+/// moving away from it doesn't count as a hit.
+fn sweep_circle_aabb(start: Pos, motion: Pos, radius: f32, min: Pos, max: Pos) -> Option<Hit> {
+    let closest = Pos::new(start.x.clamp(min.x, max.x), start.y.clamp(min.y, max.y));
+    let offset = start - closest;
+    let distance = offset.velocity();
+
+    // already overlapping, push straight out
+    if distance < radius - CONTACT_SKIN {
+        let normal = if distance > 0.0 {
+            offset * (1.0 / distance)
+        } else {
+            // the centre is inside the box, leave through the nearest face
+            let faces = [
+                (start.x - min.x, Pos::new(-1.0, 0.0)),
+                (max.x - start.x, Pos::new(1.0, 0.0)),
+                (start.y - min.y, Pos::new(0.0, -1.0)),
+                (max.y - start.y, Pos::new(0.0, 1.0)),
+            ];
+            faces.iter().min_by(|a, b| a.0.total_cmp(&b.0)).unwrap().1
+        };
+        return (motion.dot(normal) < 0.0).then_some(Hit { time: 0.0, normal });
+    }
+
+    // slab test against the grown box
+    let mut t_enter = f32::NEG_INFINITY;
+    let mut t_exit = f32::INFINITY;
+    let mut normal = Pos::default();
+    for (s, m, lo, hi, axis) in [
+        (
+            start.x,
+            motion.x,
+            min.x - radius,
+            max.x + radius,
+            Pos::new(1.0, 0.0),
+        ),
+        (
+            start.y,
+            motion.y,
+            min.y - radius,
+            max.y + radius,
+            Pos::new(0.0, 1.0),
+        ),
+    ] {
+        if m.abs() < f32::EPSILON {
+            if s < lo || s > hi {
+                return None;
+            }
+            continue;
+        }
+        let (t0, t1) = ((lo - s) / m, (hi - s) / m);
+        let (near, far) = if t0 < t1 { (t0, t1) } else { (t1, t0) };
+        if near > t_enter {
+            t_enter = near;
+            normal = axis * -m.signum();
+        }
+        t_exit = t_exit.min(far);
+    }
+    if t_enter > t_exit || t_enter > 1.0 || t_exit < 0.0 {
+        return None;
+    }
+    if t_enter < 0.0 {
+        // starting just touching, either resting against a face or next to a rounded corner
+        let inside_x = start.x >= min.x && start.x <= max.x;
+        let inside_y = start.y >= min.y && start.y <= max.y;
+        if !inside_x && !inside_y {
+            return sweep_circle_point(start, motion, radius, closest);
+        }
+        let normal = offset * (1.0 / distance);
+        return (motion.dot(normal) < 0.0).then_some(Hit { time: 0.0, normal });
+    }
+
+    // inside the span of a face, so the flat side was hit
+    let point = start + motion * t_enter;
+    let on_x_face = point.y >= min.y && point.y <= max.y;
+    let on_y_face = point.x >= min.x && point.x <= max.x;
+    if on_x_face || on_y_face {
+        return (motion.dot(normal) < 0.0).then_some(Hit {
+            time: t_enter,
+            normal,
+        });
+    }
+
+    // otherwise it can only be the rounded corner
+    let corner = Pos::new(
+        if point.x < min.x { min.x } else { max.x },
+        if point.y < min.y { min.y } else { max.y },
+    );
+    sweep_circle_point(start, motion, radius, corner)
+}
+
+/// Sweeps a circle from `start` along `motion` against the line from `a` to `b`,
+/// which is `thickness` thick either side and rounded off at the ends.
+fn sweep_circle_segment(
+    start: Pos,
+    motion: Pos,
+    radius: f32,
+    a: Pos,
+    b: Pos,
+    thickness: f32,
+) -> Option<Hit> {
+    let radius = radius + thickness;
+    let length = (b - a).velocity();
+    if length < f32::EPSILON {
+        return sweep_circle_point(start, motion, radius, a);
+    }
+    let along_line = (b - a) * (1.0 / length);
+    let across_line = Pos::new(-along_line.y, along_line.x);
+
+    // already overlapping, push straight out
+    let closest = a + along_line * (start - a).dot(along_line).clamp(0.0, length);
+    let offset = start - closest;
+    let distance = offset.velocity();
+    if distance < radius - CONTACT_SKIN {
+        let normal = if distance > 0.0 {
+            offset * (1.0 / distance)
+        } else {
+            across_line
+        };
+        return (motion.dot(normal) < 0.0).then_some(Hit { time: 0.0, normal });
+    }
+
+    // the flat side facing the circle
+    let side = (start - a).dot(across_line);
+    let normal = if side >= 0.0 {
+        across_line
+    } else {
+        across_line * -1.0
+    };
+    let closing = -motion.dot(normal);
+    if closing > 0.0 {
+        let time = ((side.abs() - radius) / closing).max(0.0);
+        if time > 1.0 {
+            return None;
+        }
+        let along = (start + motion * time - a).dot(along_line);
+        if (0.0..=length).contains(&along) {
+            return Some(Hit { time, normal });
+        }
+    }
+
+    // otherwise it can only be one of the rounded ends
+    match (
+        sweep_circle_point(start, motion, radius, a),
+        sweep_circle_point(start, motion, radius, b),
+    ) {
+        (Some(x), Some(y)) => Some(if x.time <= y.time { x } else { y }),
+        (x, y) => x.or(y),
+    }
+}
+
+/// A shape that collides with the ball.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Shape {
+    Box {
+        min: Pos,
+        max: Pos,
+    },
+    /// a line from `a` to `b`, `radius` thick either side with rounded ends
+    Capsule {
+        a: Pos,
+        b: Pos,
+        radius: f32,
+    },
+    Circle {
+        centre: Pos,
+        radius: f32,
+    },
+    /// part of a circle `radius` out from `centre`, going clockwise from the angle `from` for `sweep` radians,
+    /// `thickness` thick either side with rounded ends
+    Arc {
+        centre: Pos,
+        radius: f32,
+        from: f32,
+        sweep: f32,
+        thickness: f32,
+    },
+}
+
+impl Shape {
+    pub(crate) fn sweep(&self, start: Pos, motion: Pos, radius: f32) -> Option<Hit> {
+        match *self {
+            Shape::Box { min, max } => sweep_circle_aabb(start, motion, radius, min, max),
+            Shape::Capsule {
+                a,
+                b,
+                radius: thickness,
+            } => sweep_circle_segment(start, motion, radius, a, b, thickness),
+            Shape::Circle {
+                centre,
+                radius: size,
+            } => sweep_circle_point(start, motion, radius + size, centre),
+            Shape::Arc {
+                centre,
+                radius: size,
+                from,
+                sweep,
+                thickness,
+            } => sweep_circle_arc(start, motion, radius, centre, size, from, sweep, thickness),
+        }
+    }
+
+    /// the point on the middle line of the shape nearest to `point`, and how thick the shape is around it
+    pub(crate) fn closest(&self, point: Pos) -> (Pos, f32) {
+        match *self {
+            Shape::Box { min, max } => (
+                Pos::new(point.x.clamp(min.x, max.x), point.y.clamp(min.y, max.y)),
+                0.0,
+            ),
+            Shape::Capsule { a, b, radius } => {
+                let length = (b - a).velocity().max(f32::EPSILON);
+                let along_line = (b - a) * (1.0 / length);
+                (
+                    a + along_line * (point - a).dot(along_line).clamp(0.0, length),
+                    radius,
+                )
+            }
+            Shape::Circle { centre, radius } => (centre, radius),
+            Shape::Arc {
+                centre,
+                radius,
+                from,
+                sweep,
+                thickness,
+            } => (
+                closest_on_arc(point, centre, radius, from, sweep),
+                thickness,
+            ),
+        }
+    }
+
+    /// Which way and how far a circle has to move to stop overlapping the shape, if it does.
+    fn penetration(&self, centre: Pos, radius: f32) -> Option<(Pos, f32)> {
+        let (closest, reach, inside_normal) = match *self {
+            Shape::Box { min, max } => {
+                let closest = Pos::new(centre.x.clamp(min.x, max.x), centre.y.clamp(min.y, max.y));
+                // from inside the box, the way out is through the nearest face
+                let faces = [
+                    (centre.x - min.x, Pos::new(-1.0, 0.0)),
+                    (max.x - centre.x, Pos::new(1.0, 0.0)),
+                    (centre.y - min.y, Pos::new(0.0, -1.0)),
+                    (max.y - centre.y, Pos::new(0.0, 1.0)),
+                ];
+                let (depth, normal) = faces
+                    .into_iter()
+                    .min_by(|a, b| a.0.total_cmp(&b.0))
+                    .unwrap();
+                (closest, radius, (normal, depth + radius))
+            }
+            Shape::Capsule {
+                a,
+                b,
+                radius: thickness,
+            } => {
+                let length = (b - a).velocity().max(f32::EPSILON);
+                let along_line = (b - a) * (1.0 / length);
+                let closest = a + along_line * (centre - a).dot(along_line).clamp(0.0, length);
+                (
+                    closest,
+                    radius + thickness,
+                    (Pos::new(-along_line.y, along_line.x), radius + thickness),
+                )
+            }
+            Shape::Circle { .. } | Shape::Arc { .. } => {
+                let (closest, thickness) = self.closest(centre);
+                (
+                    closest,
+                    radius + thickness,
+                    (Pos::new(0.0, -1.0), radius + thickness),
+                )
+            }
+        };
+        let offset = centre - closest;
+        let distance = offset.velocity();
+        if distance >= reach {
+            None
+        } else if distance > 0.0 {
+            Some((offset * (1.0 / distance), reach - distance))
+        } else {
+            Some(inside_normal)
+        }
+    }
+}
+
+/// Where a moving obstacle is at one moment, and how it is moving.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct MovingShape {
+    pub(crate) shape: Shape,
+    /// how fast the whole shape is sliding (pixels per second)
+    pub(crate) velocity: Pos,
+    /// the point the shape turns about and how fast, in radians per second clockwise
+    pub(crate) spin: Option<(Pos, f32)>,
+}
+
+impl MovingShape {
+    /// how fast the part of the shape at `point` is moving
+    fn velocity_at(&self, point: Pos) -> Pos {
+        match self.spin {
+            Some((pivot, speed)) => {
+                let arm = point - pivot;
+                self.velocity + Pos::new(-arm.y, arm.x) * speed
+            }
+            None => self.velocity,
+        }
+    }
+}
+
+/// whether the direction of `offset` is within an arc going clockwise from `from` for `sweep` radians
+fn on_arc(offset: Pos, from: f32, sweep: f32) -> bool {
+    (offset.y.atan2(offset.x) - from).rem_euclid(std::f32::consts::TAU) <= sweep
+}
+
+/// the ends of an arc, where it starts and where it stops
+fn arc_ends(centre: Pos, radius: f32, from: f32, sweep: f32) -> [Pos; 2] {
+    [from, from + sweep].map(|angle| centre + Pos::new(angle.cos(), angle.sin()) * radius)
+}
+
+/// the point on an arc nearest to `point`
+fn closest_on_arc(point: Pos, centre: Pos, radius: f32, from: f32, sweep: f32) -> Pos {
+    let offset = point - centre;
+    let distance = offset.velocity();
+    if distance > 0.0 && on_arc(offset, from, sweep) {
+        return centre + offset * (radius / distance);
+    }
+    let [a, b] = arc_ends(centre, radius, from, sweep);
+    if (point - a).velocity() <= (point - b).velocity() {
+        a
+    } else {
+        b
+    }
+}
+
+/// Sweeps a circle from `start` along `motion` against a curved wall, see `Shape::Arc`.
+#[allow(clippy::too_many_arguments)]
+fn sweep_circle_arc(
+    start: Pos,
+    motion: Pos,
+    radius: f32,
+    centre: Pos,
+    arc_radius: f32,
+    from: f32,
+    sweep: f32,
+    thickness: f32,
+) -> Option<Hit> {
+    let reach = radius + thickness;
+
+    // already overlapping, push straight out
+    let offset = start - closest_on_arc(start, centre, arc_radius, from, sweep);
+    let distance = offset.velocity();
+    if distance < reach - CONTACT_SKIN {
+        let normal = if distance > 0.0 {
+            offset * (1.0 / distance)
+        } else {
+            (start - centre) * (1.0 / (start - centre).velocity().max(f32::EPSILON))
+        };
+        return (motion.dot(normal) < 0.0).then_some(Hit { time: 0.0, normal });
+    }
+
+    let mut hits = Vec::with_capacity(4);
+    let from_centre = (start - centre).velocity();
+    // the outside of the curve, from beyond it
+    if from_centre >= arc_radius + reach {
+        if let Some(hit) = sweep_circle_point(start, motion, arc_radius + reach, centre) {
+            if on_arc(start + motion * hit.time - centre, from, sweep) {
+                hits.push(hit);
+            }
+        }
+    }
+    // the inside of the curve, from within it
+    let inner = arc_radius - reach;
+    if inner > 0.0 && from_centre <= inner {
+        let offset = start - centre;
+        let a = motion.dot(motion);
+        let b = offset.dot(motion);
+        let c = offset.dot(offset) - inner * inner;
+        if a > f32::EPSILON {
+            let time = (-b + (b * b - a * c).max(0.0).sqrt()) / a;
+            let contact = offset + motion * time;
+            if time <= 1.0 && on_arc(contact, from, sweep) {
+                hits.push(Hit {
+                    time,
+                    normal: contact * (-1.0 / contact.velocity().max(f32::EPSILON)),
+                });
+            }
+        }
+    }
+    // the rounded ends
+    for end in arc_ends(centre, arc_radius, from, sweep) {
+        hits.extend(sweep_circle_point(start, motion, reach, end));
+    }
+    hits.into_iter().min_by(|a, b| a.time.total_cmp(&b.time))
+}
+
+/// Sweeps a circle from `start` along `motion` against a single point.
+fn sweep_circle_point(start: Pos, motion: Pos, radius: f32, point: Pos) -> Option<Hit> {
+    let offset = start - point;
+    let a = motion.dot(motion);
+    let b = offset.dot(motion);
+    let c = offset.dot(offset) - radius * radius;
+    if a < f32::EPSILON || b >= 0.0 {
+        // not moving, or moving away
+        return None;
+    }
+    let discriminant = b * b - a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let time = ((-b - discriminant.sqrt()) / a).max(0.0);
+    if time > 1.0 {
+        return None;
+    }
+    let contact = start + motion * time - point;
+    let length = contact.velocity();
+    let normal = if length > 0.0 {
+        contact * (1.0 / length)
+    } else {
+        offset * (1.0 / offset.velocity())
+    };
+    Some(Hit { time, normal })
+}
+
+impl Default for Pos {
+    fn default() -> Self {
+        Self { x: 0.0, y: 0.0 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Pos, b: Pos) -> bool {
+        (a - b).velocity() < 1e-4
+    }
+
+    #[test]
+    fn fastest_shot_does_not_tunnel_through_a_one_cell_wall() {
+        let mut map = GolfMap::default();
+        map.objects.push(GameObject::Wall {
+            a: Point { x: 10, y: 0 },
+            b: Point { x: 10, y: 19 },
+        });
+        map.update_hashmap();
+        map.update_heightmap();
+        let wall_face = 10.0 * map.cell_size;
+
+        let mut ball = GolfBall::default();
+        ball.place(map.cell_centre(Point { x: 8, y: 10 }));
+        ball.vel = Pos::new(AIM_REACH * AIM_STRENGTH, 0.0);
+        let mut hits = 0;
+        for tick in 0..240 {
+            hits += ball.update_pos(&map, tick).wall_hits;
+            assert!(
+                ball.pos.x <= wall_face - BALL_RADIUS + CONTACT_SKIN * 2.0,
+                "went through at {:?}",
+                ball.pos
+            );
+        }
+        assert!(hits > 0);
+    }
+
+    #[test]
+    fn corner_hit_normal_is_diagonal() {
+        let min = Pos::new(0.0, 0.0);
+        let max = Pos::new(10.0, 10.0);
+        let hit =
+            sweep_circle_aabb(Pos::new(-10.0, -10.0), Pos::new(20.0, 20.0), 5.0, min, max).unwrap();
+        let diagonal = -std::f32::consts::FRAC_1_SQRT_2;
+        assert!(
+            close(hit.normal, Pos::new(diagonal, diagonal)),
+            "{:?}",
+            hit.normal
+        );
+        assert!(hit.time > 0.0 && hit.time < 1.0);
+    }
+
+    #[test]
+    fn face_hit_normal_is_straight() {
+        let hit = sweep_circle_aabb(
+            Pos::new(-20.0, 5.0),
+            Pos::new(20.0, 0.0),
+            5.0,
+            Pos::new(0.0, 0.0),
+            Pos::new(10.0, 10.0),
+        )
+        .unwrap();
+        assert!(close(hit.normal, Pos::new(-1.0, 0.0)));
+        assert!((hit.time - 0.75).abs() < 1e-4);
+    }
+
+    #[test]
+    fn restitution_scales_the_reflected_normal_speed() {
+        let normal = Pos::new(-1.0, 0.0);
+        let velocity = Pos::new(100.0, -50.0);
+        assert!(close(
+            velocity.reflect(normal, 1.0),
+            Pos::new(-100.0, -50.0)
+        ));
+        assert!(close(velocity.reflect(normal, 0.5), Pos::new(-50.0, -50.0)));
+        assert!(close(velocity.reflect(normal, 0.0), Pos::new(0.0, -50.0)));
+        // already moving away from the surface
+        assert!(close(
+            Pos::new(-100.0, 0.0).reflect(normal, 0.5),
+            Pos::new(-100.0, 0.0)
+        ));
+    }
+
+    #[test]
+    fn wall_bounce_keeps_the_maps_restitution() {
+        let mut map = GolfMap::default();
+        map.restitution = 0.5;
+        map.objects.push(GameObject::Wall {
+            a: Point { x: 10, y: 0 },
+            b: Point { x: 10, y: 19 },
+        });
+        map.update_hashmap();
+        let start = Pos::new(10.0 * map.cell_size - BALL_RADIUS - 1.0, 100.0);
+        let (hit, restitution) = map
+            .first_wall_hit(start, Pos::new(10.0, 0.0), BALL_RADIUS, &[])
+            .unwrap();
+        assert_eq!(restitution, 0.5);
+        assert!(close(
+            Pos::new(10.0, 0.0).reflect(hit.normal, restitution),
+            Pos::new(-5.0, 0.0)
+        ));
+    }
+
+    #[test]
+    fn touching_while_moving_away_is_not_a_hit() {
+        let (min, max) = (Pos::new(0.0, 0.0), Pos::new(10.0, 10.0));
+        let away = Pos::new(10.0, 0.0);
+        // just touching the right face, and sunk a little way into it
+        for x in [15.0, 14.0] {
+            assert_eq!(
+                sweep_circle_aabb(Pos::new(x, 5.0), away, 5.0, min, max),
+                None
+            );
+        }
+        // moving into it from the same places does hit
+        assert!(sweep_circle_aabb(Pos::new(14.0, 5.0), away * -1.0, 5.0, min, max).is_some());
+
+        let (a, b) = (Pos::new(0.0, 0.0), Pos::new(0.0, 100.0));
+        assert_eq!(
+            sweep_circle_segment(Pos::new(7.0, 50.0), away, 5.0, a, b, 2.0),
+            None
+        );
+        assert_eq!(
+            sweep_circle_segment(Pos::new(6.0, 50.0), away, 5.0, a, b, 2.0),
+            None
+        );
+
+        let arc = Shape::Arc {
+            centre: Pos::new(0.0, 0.0),
+            radius: 50.0,
+            from: 0.0,
+            sweep: std::f32::consts::PI,
+            thickness: 2.0,
+        };
+        assert_eq!(
+            arc.sweep(Pos::new(0.0, 57.0), Pos::new(0.0, 10.0), 5.0),
+            None
+        );
+        assert_eq!(
+            arc.sweep(Pos::new(0.0, 43.0), Pos::new(0.0, -10.0), 5.0),
+            None
+        );
+    }
+
+    #[test]
+    fn curved_wall_is_hit_from_either_side() {
+        let arc = Shape::Arc {
+            centre: Pos::new(0.0, 0.0),
+            radius: 50.0,
+            from: 0.0,
+            sweep: std::f32::consts::PI,
+            thickness: 2.0,
+        };
+        // from outside, coming up towards the bottom of the curve
+        let hit = arc
+            .sweep(Pos::new(0.0, 80.0), Pos::new(0.0, -40.0), 5.0)
+            .unwrap();
+        assert!(close(hit.normal, Pos::new(0.0, 1.0)), "{:?}", hit.normal);
+        assert!((hit.time - 23.0 / 40.0).abs() < 1e-4);
+        // from inside, going down into it
+        let hit = arc
+            .sweep(Pos::new(0.0, 0.0), Pos::new(0.0, 60.0), 5.0)
+            .unwrap();
+        assert!(close(hit.normal, Pos::new(0.0, -1.0)), "{:?}", hit.normal);
+        assert!((hit.time - 43.0 / 60.0).abs() < 1e-4);
+        // the top half is open
+        assert_eq!(
+            arc.sweep(Pos::new(0.0, 0.0), Pos::new(0.0, -60.0), 5.0),
+            None
+        );
+    }
+}
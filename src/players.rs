@@ -0,0 +1,153 @@
+use strum_macros::EnumIter;
+
+use crate::map::GolfMap;
+use crate::physics::{GolfBall, Pos};
+
+/// most people that can take turns on one device
+pub(crate) const MAX_PLAYERS: usize = 6;
+
+/// who plays next once every ball has stopped
+#[derive(
+    serde::Deserialize, serde::Serialize, Clone, Copy, Debug, Default, PartialEq, Eq, EnumIter,
+)]
+pub(crate) enum TurnOrder {
+    /// whoever is furthest from the cup, the way golf is usually played
+    #[default]
+    FurthestFromCup,
+    /// everyone in turn, in the order they are listed
+    Rotation,
+}
+
+impl TurnOrder {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            TurnOrder::FurthestFromCup => "Furthest from the cup",
+            TurnOrder::Rotation => "Take turns",
+        }
+    }
+}
+
+/// Someone playing the course, with their own ball and scores.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, Default)]
+#[serde(default)]
+pub(crate) struct Player {
+    pub(crate) name: String,
+    pub(crate) ball: GolfBall,
+    /// strokes taken on the hole being played
+    pub(crate) strokes: u32,
+    /// strokes taken on each hole of the course this round, `None` if it has not been finished
+    pub(crate) scores: Vec<Option<u32>>,
+    /// hints asked for on the hole being played
+    pub(crate) hints_taken: u32,
+    /// hints asked for on each hole of the course this round
+    pub(crate) hints: Vec<u32>,
+    /// whether the ball has been hit on this hole, penalty strokes can come before that
+    hit: bool,
+}
+
+impl Player {
+    pub(crate) fn new(name: String, holes: usize) -> Self {
+        Self {
+            name,
+            scores: vec![None; holes],
+            hints: vec![0; holes],
+            ..Default::default()
+        }
+    }
+
+    /// Puts the ball on the tee with nothing taken on the hole yet.
+    pub(crate) fn start_hole(&mut self, tee: Pos) {
+        self.ball.place(tee);
+        self.strokes = 0;
+        self.hints_taken = 0;
+        self.hit = false;
+    }
+
+    /// Carries on with a ball part way through a hole, as saved from before there were players.
+    pub(crate) fn take_over(&mut self, ball: GolfBall, strokes: u32) {
+        self.ball = ball;
+        self.strokes = strokes;
+        self.hit = strokes > 0;
+    }
+
+    /// Clears the scorecard for a course of `holes` holes.
+    pub(crate) fn new_round(&mut self, holes: usize) {
+        self.scores = vec![None; holes];
+        self.hints = vec![0; holes];
+    }
+
+    /// Keeps the scorecard the same length as a course of `holes` holes.
+    pub(crate) fn resize_round(&mut self, holes: usize) {
+        self.scores.resize(holes, None);
+        self.hints.resize(holes, 0);
+    }
+
+    /// Takes a hole that has been removed from the course off the scorecard.
+    pub(crate) fn remove_hole(&mut self, hole: usize) {
+        if hole < self.scores.len() {
+            self.scores.remove(hole);
+        }
+        if hole < self.hints.len() {
+            self.hints.remove(hole);
+        }
+    }
+
+    pub(crate) fn score(&self, hole: usize) -> Option<u32> {
+        self.scores.get(hole).copied().flatten()
+    }
+
+    /// whether the ball has been hit on this hole, until then it waits at the start without getting in anyone's way
+    pub(crate) fn teed_off(&self) -> bool {
+        self.hit
+    }
+
+    /// Hits the ball, counting the stroke.
+    pub(crate) fn shoot(&mut self, velocity: Pos) {
+        self.ball.vel = velocity;
+        self.strokes += 1;
+        self.hit = true;
+    }
+}
+
+/// Moves every ball on by one physics step, adding a stroke for each one that goes in the water.
+pub(crate) fn step(players: &mut [Player], map: &GolfMap, tick: u32, balls_collide: bool) {
+    for player in players.iter_mut() {
+        if player.ball.update_pos(map, tick).splashed {
+            player.strokes += 1;
+        }
+    }
+    if balls_collide {
+        // balls waiting at the start roll through everything until they are hit
+        for j in 1..players.len() {
+            let (before, after) = players.split_at_mut(j);
+            let other = &mut after[0];
+            for player in before.iter_mut().filter(|p| p.teed_off()) {
+                if other.teed_off() {
+                    player.ball.collide(&mut other.ball);
+                }
+            }
+        }
+    }
+}
+
+/// Picks who plays after `current` out of the players whose ball isn't in the cup yet.
+pub(crate) fn next_player(
+    players: &[Player],
+    current: usize,
+    order: TurnOrder,
+    map: &GolfMap,
+) -> Option<usize> {
+    // everyone after the current player, coming back round to them last
+    let mut waiting = (1..=players.len())
+        .map(|step| (current + step) % players.len())
+        .filter(|i| !players[*i].ball.sunk);
+    let cup = map.hole().map(|hole| map.cell_centre(hole));
+    match (order, cup) {
+        // a tie goes to whoever comes round first
+        (TurnOrder::FurthestFromCup, Some(cup)) => waiting.min_by(|a, b| {
+            let distance = |i: &usize| (players[*i].ball.pos - cup).velocity();
+            distance(b).total_cmp(&distance(a))
+        }),
+        _ => waiting.next(),
+    }
+}
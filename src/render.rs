@@ -0,0 +1,369 @@
+use egui::{Align2, Color32, FontId, Mesh, Painter, Pos2, Rect, Stroke};
+
+use crate::map::{Direction, GameObject, GolfMap, Point, Surface};
+use crate::physics::{GolfBall, Pos, Shape, AIM_REACH, AIM_STRENGTH, CUP_RADIUS};
+
+const WALL_COLOUR: Color32 = Color32::BLACK;
+const HOLE_RING_COLOUR: Color32 = Color32::from_rgb(255, 0, 0);
+const SELECTION_COLOUR: Color32 = Color32::from_rgb(255, 220, 0);
+const CONVEYOR_COLOUR: Color32 = Color32::from_gray(110);
+const BOOSTER_COLOUR: Color32 = Color32::from_rgb(255, 150, 20);
+const BUMPER_COLOUR: Color32 = Color32::from_rgb(220, 40, 60);
+/// each player's ball is a different colour, in the order they are listed
+pub(crate) const BALL_COLOURS: [Color32; 6] = [
+    Color32::WHITE,
+    Color32::from_rgb(255, 215, 0),
+    Color32::from_rgb(255, 110, 80),
+    Color32::from_rgb(90, 190, 255),
+    Color32::from_rgb(255, 120, 220),
+    Color32::from_rgb(170, 130, 255),
+];
+/// the dots showing where a shot is going
+pub(crate) const PREVIEW_COLOUR: Color32 = Color32::from_rgba_premultiplied(180, 180, 180, 180);
+const HINT_COLOUR: Color32 = Color32::from_rgb(255, 230, 60);
+/// physics steps between each dot of the aim preview
+const PREVIEW_STEPS_PER_DOT: usize = 6;
+/// the ball from the best previous attempt at a hole, see-through so it doesn't get mistaken for a real one
+pub(crate) const GHOST_COLOUR: Color32 = Color32::from_rgba_premultiplied(90, 90, 90, 90);
+/// teleporter pads take these colours in turn, so the two pads of a pair match each other
+const TELEPORTER_COLOURS: [Color32; 6] = [
+    Color32::from_rgb(170, 90, 230),
+    Color32::from_rgb(240, 130, 40),
+    Color32::from_rgb(40, 200, 220),
+    Color32::from_rgb(230, 70, 150),
+    Color32::from_rgb(250, 240, 90),
+    Color32::from_rgb(120, 120, 255),
+];
+
+fn surface_colour(surface: Surface) -> Color32 {
+    match surface {
+        Surface::Sand => Color32::from_rgb(222, 196, 126),
+        Surface::Ice => Color32::from_rgb(196, 232, 245),
+        Surface::Rough => Color32::from_rgb(22, 94, 44),
+        Surface::Water => Color32::from_rgb(40, 110, 200),
+    }
+}
+
+/// colour of the grass in a cell, higher ground is lighter
+fn grass_colour(map: &GolfMap, point: &Point) -> Color32 {
+    Color32::from_rgb(0, (136 + map.height_at(point) * 2).clamp(30, 255) as u8, 84)
+}
+
+/// the area a cell covers on screen, for a green with its top left corner at `origin`
+pub(crate) fn cell_rect(map: &GolfMap, origin: Pos2, point: Point) -> Rect {
+    let min = origin + egui::vec2(point.x as f32, point.y as f32) * map.cell_size;
+    Rect::from_min_size(min, egui::Vec2::splat(map.cell_size))
+}
+
+/// Paints the green with its top left corner at `origin`, and its moving obstacles where they are after `tick` physics steps.
+///
+/// Every cell goes into a single mesh so even the biggest greens are one shape to draw.
+/// When `editing`, each object shows its symbol and the cell under `hover` is highlighted.
+pub(crate) fn paint_green(
+    painter: &Painter,
+    origin: Pos2,
+    map: &GolfMap,
+    tick: u32,
+    editing: bool,
+    hover: Option<Point>,
+) {
+    let mut mesh = Mesh::default();
+    let cells = (map.width * map.height).max(0) as usize;
+    mesh.reserve_vertices(cells * 4);
+    mesh.reserve_triangles(cells * 2);
+    for y in 0..map.height {
+        for x in 0..map.width {
+            let point = Point { x, y };
+            let colour = match map.get_point(&point) {
+                Some(GameObject::Wall { .. }) => WALL_COLOUR,
+                _ if map.conveyor_at(&point).is_some() => CONVEYOR_COLOUR,
+                _ => match map.surface_at(&point) {
+                    Some(surface) => surface_colour(surface),
+                    None => grass_colour(map, &point),
+                },
+            };
+            mesh.add_colored_rect(cell_rect(map, origin, point), colour);
+        }
+    }
+    painter.add(egui::Shape::mesh(mesh));
+
+    if let Some(hole) = map.hole() {
+        painter.circle_stroke(
+            cell_rect(map, origin, hole).center(),
+            CUP_RADIUS,
+            Stroke::new(2.0, HOLE_RING_COLOUR),
+        );
+    }
+
+    let at = |p: Pos| origin + egui::vec2(p.x, p.y);
+
+    // each pair of pads shares a colour and a number
+    let teleporters = map.objects.iter().filter_map(|o| match o {
+        GameObject::Teleporter { a, b, turn } => Some((*a, *b, *turn)),
+        _ => None,
+    });
+    for (i, (a, b, turn)) in teleporters.enumerate() {
+        let colour = TELEPORTER_COLOURS[i % TELEPORTER_COLOURS.len()];
+        if editing {
+            painter.line_segment(
+                [at(map.cell_centre(a)), at(map.cell_centre(b))],
+                Stroke::new(1.5, colour.linear_multiply(0.6)),
+            );
+        }
+        // turning pads get an arrow round the number
+        let label = match turn {
+            1 => format!("{}↻", i + 1),
+            2 => format!("{}⬍", i + 1),
+            3 => format!("{}↺", i + 1),
+            _ => format!("{}", i + 1),
+        };
+        for pad in [a, b] {
+            let centre = at(map.cell_centre(pad));
+            painter.circle(
+                centre,
+                map.cell_size * 0.45,
+                colour.linear_multiply(0.5),
+                Stroke::new(2.0, colour),
+            );
+            painter.text(
+                centre,
+                Align2::CENTER_CENTER,
+                &label,
+                FontId::proportional(map.cell_size * 0.45),
+                Color32::WHITE,
+            );
+        }
+    }
+
+    // an arrow on every cell of belt, and a bigger one on each booster
+    let arrow = |point: Point, direction: Direction, length: f32, stroke: Stroke| {
+        let vector = direction.vector();
+        let vector = egui::vec2(vector.x, vector.y) * length;
+        painter.arrow(
+            cell_rect(map, origin, point).center() - vector / 2.0,
+            vector,
+            stroke,
+        );
+    };
+    for y in 0..map.height {
+        for x in 0..map.width {
+            let point = Point { x, y };
+            if let Some(direction) = map.conveyor_at(&point) {
+                arrow(
+                    point,
+                    direction,
+                    map.cell_size * 0.5,
+                    Stroke::new(1.5, Color32::from_gray(200)),
+                );
+            }
+        }
+    }
+    for obj in &map.objects {
+        if let GameObject::Booster { pos, direction } = obj {
+            painter.rect_filled(
+                cell_rect(map, origin, *pos).shrink(1.0),
+                2.0,
+                BOOSTER_COLOUR.linear_multiply(0.6),
+            );
+            arrow(
+                *pos,
+                *direction,
+                map.cell_size * 0.7,
+                Stroke::new(map.cell_size / 6.0, BOOSTER_COLOUR),
+            );
+        }
+    }
+
+    if editing {
+        // the track each sliding block runs along
+        for obj in &map.objects {
+            if let GameObject::SlidingBlock { a, b, .. } = obj {
+                painter.line_segment(
+                    [at(map.cell_centre(*a)), at(map.cell_centre(*b))],
+                    Stroke::new(map.cell_size / 4.0, Color32::from_black_alpha(60)),
+                );
+            }
+        }
+    }
+    for obj in &map.objects {
+        match (obj, obj.shape(map.cell_size)) {
+            (GameObject::Bumper { .. }, Some(Shape::Circle { centre, radius })) => {
+                painter.circle(
+                    at(centre),
+                    radius,
+                    BUMPER_COLOUR,
+                    Stroke::new(2.0, Color32::WHITE),
+                );
+            }
+            (_, Some(shape)) => paint_shape(painter, origin, shape, WALL_COLOUR),
+            _ => {}
+        }
+    }
+    for obstacle in map.moving_shapes(tick) {
+        paint_shape(painter, origin, obstacle.shape, WALL_COLOUR);
+        if let Some((pivot, _)) = obstacle.spin {
+            painter.circle(
+                at(pivot),
+                map.cell_size / 3.0,
+                Color32::from_gray(90),
+                Stroke::new(1.0, WALL_COLOUR),
+            );
+        }
+    }
+
+    if editing {
+        let font = FontId::proportional(map.cell_size * 0.6);
+        for (point, obj) in &map.map {
+            // teleporter pads have their pair number on them instead, and boosters their arrow
+            if !map.contains(point)
+                || matches!(
+                    obj,
+                    GameObject::Teleporter { .. } | GameObject::Booster { .. }
+                )
+            {
+                continue;
+            }
+            painter.text(
+                cell_rect(map, origin, *point).center(),
+                Align2::CENTER_CENTER,
+                obj.symbol(),
+                font.clone(),
+                Color32::WHITE,
+            );
+        }
+        if let Some(point) = hover {
+            painter.rect_filled(
+                cell_rect(map, origin, point),
+                0.0,
+                Color32::from_white_alpha(40),
+            );
+        }
+    }
+}
+
+/// Paints a shape that collides with the ball, on a green with its top left corner at `origin`.
+pub(crate) fn paint_shape(painter: &Painter, origin: Pos2, shape: Shape, colour: Color32) {
+    let at = |p: Pos| origin + egui::vec2(p.x, p.y);
+    match shape {
+        Shape::Box { min, max } => {
+            painter.rect_filled(Rect::from_min_max(at(min), at(max)), 2.0, colour)
+        }
+        Shape::Capsule { a, b, radius } => {
+            painter.line_segment([at(a), at(b)], Stroke::new(radius * 2.0, colour));
+            painter.circle_filled(at(a), radius, colour);
+            painter.circle_filled(at(b), radius, colour);
+        }
+        Shape::Circle { centre, radius } => painter.circle_filled(at(centre), radius, colour),
+        Shape::Arc {
+            centre,
+            radius,
+            from,
+            sweep,
+            thickness,
+        } => {
+            // short enough straight pieces look like a curve
+            let pieces = ((sweep * radius / 4.0).ceil() as usize).clamp(2, 256);
+            let points: Vec<Pos2> = (0..=pieces)
+                .map(|i| from + sweep * i as f32 / pieces as f32)
+                .map(|angle| at(centre + Pos::new(angle.cos(), angle.sin()) * radius))
+                .collect();
+            let ends = [points[0], points[pieces]];
+            painter.add(egui::Shape::line(
+                points,
+                Stroke::new(thickness * 2.0, colour),
+            ));
+            for end in ends {
+                painter.circle_filled(end, thickness, colour);
+            }
+        }
+    }
+}
+
+/// Paints a ball on a green with its top left corner at `origin`, smaller once it has dropped into the cup.
+pub(crate) fn paint_ball(painter: &Painter, origin: Pos2, ball: &GolfBall, colour: Color32) {
+    let at = origin + egui::vec2(ball.pos.x, ball.pos.y);
+    // the outline is as see-through as the ball
+    painter.circle(
+        at,
+        if ball.sunk { 5.0 } else { 8.0 },
+        colour,
+        Stroke::new(1.0, Color32::from_black_alpha(colour.a())),
+    );
+}
+
+/// Paints dots along the path `ball` is about to take, stopping short if it goes in the water.
+pub(crate) fn paint_preview(
+    painter: &Painter,
+    origin: Pos2,
+    map: &GolfMap,
+    mut ball: GolfBall,
+    tick: u32,
+    colour: Color32,
+) {
+    let mut tick = tick;
+    for i in (0..14).rev() {
+        for _ in 0..PREVIEW_STEPS_PER_DOT {
+            if ball.update_pos(map, tick).splashed {
+                return;
+            }
+            tick = tick.wrapping_add(1);
+        }
+        painter.circle_filled(
+            origin + egui::vec2(ball.pos.x, ball.pos.y),
+            (i as f32 / 4.0) + 4.0,
+            colour,
+        );
+    }
+}
+
+/// Paints a suggested shot for `ball`: where to pull the pointer back to, how hard that hits it and where it goes.
+pub(crate) fn paint_hint(
+    painter: &Painter,
+    origin: Pos2,
+    map: &GolfMap,
+    ball: &GolfBall,
+    velocity: Pos,
+    tick: u32,
+) {
+    let from = origin + egui::vec2(ball.pos.x, ball.pos.y);
+    let aim = from - egui::vec2(velocity.x, velocity.y) / AIM_STRENGTH;
+    painter.line_segment([from, aim], Stroke::new(2.0, HINT_COLOUR));
+    painter.circle_stroke(aim, 5.0, Stroke::new(2.0, HINT_COLOUR));
+    let power = velocity.velocity() / (AIM_REACH * AIM_STRENGTH) * 100.0;
+    painter.text(
+        aim + egui::vec2(0.0, -8.0),
+        Align2::CENTER_BOTTOM,
+        format!("{power:.0}%"),
+        FontId::proportional(14.0),
+        HINT_COLOUR,
+    );
+    let mut shot = *ball;
+    shot.vel = velocity;
+    paint_preview(
+        painter,
+        origin,
+        map,
+        shot,
+        tick,
+        HINT_COLOUR.gamma_multiply(0.7),
+    );
+}
+
+/// Outlines an object picked out with the select tool, with a handle on each end that can be dragged.
+pub(crate) fn paint_selection(painter: &Painter, origin: Pos2, map: &GolfMap, object: &GameObject) {
+    let points = object.get_points();
+    let bounds = points
+        .iter()
+        .map(|p| cell_rect(map, origin, *p))
+        .fold(Rect::NOTHING, |bounds, cell| bounds.union(cell));
+    painter.rect_stroke(bounds, 2.0, Stroke::new(2.0, SELECTION_COLOUR));
+    if points.len() > 1 {
+        for point in points {
+            painter.rect_filled(
+                cell_rect(map, origin, point).shrink(map.cell_size / 4.0),
+                1.0,
+                SELECTION_COLOUR,
+            );
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use crate::map::GolfMap;
+use crate::map_code::MapCodeError;
+use crate::physics::{Pos, PHYSICS_DT};
+use crate::players::{self, Player};
+use crate::solver::Solution;
+
+/// longest the balls are left rolling after the last shot before the replay ends (seconds)
+const MAX_ROLL_TIME: f32 = 60.0;
+/// longest a replay can wait before its first shot or between shots (seconds),
+/// so a replay code can't ask for hours of physics before it can be shown
+pub(crate) const MAX_SHOT_GAP: f32 = 600.0;
+/// longest a replay plays for however many shots it has (seconds)
+const MAX_REPLAY_TIME: f32 = 1800.0;
+/// playback speeds to pick from, as multiples of real time
+pub(crate) const SPEEDS: [f32; 6] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+
+/// One hit of a ball, enough to play it again through the physics.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Shot {
+    /// index of the player who took it
+    pub(crate) player: usize,
+    /// physics steps since the hole was started when the ball was hit
+    pub(crate) tick: u32,
+    /// where the ball was hit from
+    pub(crate) start: Pos,
+    pub(crate) velocity: Pos,
+}
+
+/// Every shot taken on a hole, in the order they were taken.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub(crate) struct Replay {
+    /// code of the map the shots were taken on
+    pub(crate) code: String,
+    /// how many people were playing
+    pub(crate) players: usize,
+    pub(crate) balls_collide: bool,
+    pub(crate) shots: Vec<Shot>,
+}
+
+impl Replay {
+    pub(crate) fn new(code: String, players: usize, balls_collide: bool) -> Self {
+        Self {
+            code,
+            players,
+            balls_collide,
+            shots: Vec::new(),
+        }
+    }
+
+    /// The shots the solver found, as a replay of one player taking them.
+    pub(crate) fn from_solution(code: String, solution: &Solution) -> Replay {
+        let shots = solution
+            .shots
+            .iter()
+            .map(|s| Shot {
+                player: 0,
+                tick: s.tick,
+                start: s.from,
+                velocity: s.velocity,
+            })
+            .collect();
+        Replay {
+            code,
+            players: 1,
+            balls_collide: false,
+            shots,
+        }
+    }
+
+    /// The shots one player took, as a replay of them playing on their own.
+    /// Each shot still starts where it did, even if another ball knocked theirs there.
+    pub(crate) fn for_player(&self, player: usize) -> Replay {
+        let shots = self
+            .shots
+            .iter()
+            .filter(|s| s.player == player)
+            .map(|s| Shot { player: 0, ..*s })
+            .collect();
+        Replay {
+            code: self.code.clone(),
+            players: 1,
+            balls_collide: false,
+            shots,
+        }
+    }
+}
+
+/// The fewest strokes a hole has been finished in, and the shots that did it.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub(crate) struct PersonalBest {
+    pub(crate) strokes: u32,
+    pub(crate) replay: Replay,
+}
+
+/// Plays a replay back by simulating its shots again, so it can be paused and scrubbed through.
+pub(crate) struct ReplayViewer {
+    pub(crate) replay: Replay,
+    pub(crate) map: GolfMap,
+    pub(crate) players: Vec<Player>,
+    /// physics steps since the hole was started, moving obstacles go by this
+    pub(crate) tick: u32,
+    /// index of the first shot in `replay` that hasn't been taken yet
+    next_shot: usize,
+    /// the tick everything has stopped after the last shot
+    pub(crate) length: u32,
+    pub(crate) playing: bool,
+    /// multiple of real time the replay plays at
+    pub(crate) speed: f32,
+    /// time that has passed but not been simulated yet
+    accumulator: f32,
+}
+
+impl ReplayViewer {
+    pub(crate) fn new(replay: Replay) -> Result<Self, MapCodeError> {
+        let map = GolfMap::from_text(&replay.code)?;
+        let mut viewer = Self {
+            replay,
+            map,
+            players: Vec::new(),
+            tick: 0,
+            next_shot: 0,
+            length: 0,
+            playing: true,
+            speed: 1.0,
+            accumulator: 0.0,
+        };
+        // play it through once to find out how long it is
+        let max_roll = (MAX_ROLL_TIME / PHYSICS_DT) as u32;
+        let max_length = (MAX_REPLAY_TIME / PHYSICS_DT) as u32;
+        let last_shot = viewer.replay.shots.last().map_or(0, |s| s.tick);
+        viewer.restart();
+        while viewer.next_shot < viewer.replay.shots.len()
+            || !viewer.players.iter().all(|p| p.ball.at_rest())
+        {
+            if viewer.tick >= last_shot.saturating_add(max_roll) || viewer.tick >= max_length {
+                break;
+            }
+            viewer.step();
+        }
+        viewer.length = viewer.tick;
+        viewer.restart();
+        Ok(viewer)
+    }
+
+    /// Puts every ball back at the start, ready to play from the first shot.
+    fn restart(&mut self) {
+        let start = self
+            .map
+            .start()
+            .map(|s| self.map.cell_centre(s))
+            .unwrap_or_default();
+        self.players = (0..self.replay.players.max(1))
+            .map(|i| {
+                let mut player = Player::new(format!("Player {}", i + 1), 0);
+                player.start_hole(start);
+                player
+            })
+            .collect();
+        self.tick = 0;
+        self.next_shot = 0;
+        self.accumulator = 0.0;
+    }
+
+    /// Takes any shots due this tick, then moves everything on by one physics step.
+    fn step(&mut self) {
+        while let Some(shot) = self
+            .replay
+            .shots
+            .get(self.next_shot)
+            .filter(|s| s.tick <= self.tick)
+        {
+            if let Some(player) = self.players.get_mut(shot.player) {
+                player.ball.pos = shot.start;
+                player.shoot(shot.velocity);
+            }
+            self.next_shot += 1;
+        }
+        players::step(
+            &mut self.players,
+            &self.map,
+            self.tick,
+            self.replay.balls_collide,
+        );
+        self.tick = self.tick.wrapping_add(1);
+    }
+
+    /// Jumps to a tick, playing the replay again from the start to get there if it is behind.
+    pub(crate) fn seek(&mut self, tick: u32) {
+        let tick = tick.min(self.length);
+        if tick < self.tick {
+            self.restart();
+        }
+        while self.tick < tick {
+            self.step();
+        }
+    }
+
+    /// Plays on by `dt` seconds of real time when playing, stopping at the end.
+    pub(crate) fn advance(&mut self, dt: f32) {
+        if !self.playing {
+            return;
+        }
+        self.accumulator += dt * self.speed;
+        while self.accumulator >= PHYSICS_DT && self.tick < self.length {
+            self.step();
+            self.accumulator -= PHYSICS_DT;
+        }
+        if self.tick >= self.length {
+            self.playing = false;
+            self.accumulator = 0.0;
+        }
+    }
+
+    pub(crate) fn shots_taken(&self) -> usize {
+        self.next_shot
+    }
+
+    /// Starts playing, from the beginning again if it had reached the end.
+    pub(crate) fn play(&mut self) {
+        if self.tick >= self.length {
+            self.restart();
+        }
+        self.playing = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::{GameObject, Point};
+
+    #[test]
+    fn a_replay_can_only_be_so_long() {
+        let mut map = GolfMap::default();
+        map.objects = vec![GameObject::Start(Point { x: 1, y: 10 })];
+        let shot = Shot {
+            player: 0,
+            tick: u32::MAX,
+            start: Pos::new(30.0, 210.0),
+            velocity: Pos::new(100.0, 0.0),
+        };
+        let replay = Replay {
+            code: map.to_text(),
+            players: 1,
+            balls_collide: false,
+            shots: vec![shot],
+        };
+        let mut viewer = ReplayViewer::new(replay).unwrap();
+        assert_eq!(viewer.length, (MAX_REPLAY_TIME / PHYSICS_DT) as u32);
+        viewer.seek(u32::MAX);
+        assert_eq!(viewer.tick, viewer.length);
+    }
+}
@@ -0,0 +1,228 @@
+use crate::map::GolfMap;
+use crate::map_code::MapCodeError;
+use crate::physics::{GolfBall, Pos, PHYSICS_DT};
+use crate::solver::{self, Solution, SolverSettings};
+
+/// longest a single shot is simulated before it is called to a halt (seconds)
+const MAX_SHOT_TIME: f32 = 60.0;
+
+/// What happened during a shot.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShotReport {
+    /// where the ball ended up
+    pub final_position: Pos,
+    /// strokes taken on the hole so far, this shot included
+    pub strokes: u32,
+    /// how many times the ball bounced off a wall or the edge of the green
+    pub wall_hits: u32,
+    /// whether the ball finished in the cup
+    pub sunk: bool,
+    /// physics steps it took for the ball to stop
+    pub steps: u32,
+    /// strokes added for going in the water, already counted in `strokes`
+    pub penalty_strokes: u32,
+}
+
+/// What happened while a ball rolled to a stop.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Roll {
+    pub(crate) wall_hits: u32,
+    pub(crate) steps: u32,
+    /// times the ball went in the water, a penalty stroke each
+    pub(crate) splashes: u32,
+}
+
+/// Runs the physics until `ball` stops, or for `MAX_SHOT_TIME` if it never does, moving `tick` on with it.
+pub(crate) fn roll(ball: &mut GolfBall, map: &GolfMap, tick: &mut u32) -> Roll {
+    let mut roll = Roll::default();
+    let max_steps = (MAX_SHOT_TIME / PHYSICS_DT) as u32;
+    while !ball.at_rest() && roll.steps < max_steps {
+        let events = ball.update_pos(map, *tick);
+        *tick = tick.wrapping_add(1);
+        roll.wall_hits += events.wall_hits;
+        roll.splashes += events.splashed as u32;
+        roll.steps += 1;
+    }
+    ball.vel = Pos::default();
+    roll
+}
+
+/// A hole played without any UI, driven by the same physics as the app.
+///
+/// Load a map from its share code, then `shoot` the ball and read back where it went.
+pub struct Simulation {
+    map: GolfMap,
+    ball: GolfBall,
+    strokes: u32,
+    /// physics steps since the hole was started, moving obstacles go by this
+    tick: u32,
+}
+
+impl Simulation {
+    /// Loads a map from the code shown in the text box at the bottom of the app.
+    pub fn from_code(code: &str) -> Result<Self, MapCodeError> {
+        let mut new = Self {
+            map: GolfMap::from_text(code)?,
+            ball: GolfBall::default(),
+            strokes: 0,
+            tick: 0,
+        };
+        new.reset();
+        Ok(new)
+    }
+
+    /// Puts the ball back on the tee, clears the stroke count and sends any moving obstacles back to the start.
+    pub fn reset(&mut self) {
+        self.tick = 0;
+        self.ball = GolfBall::default();
+        self.ball
+            .place(self.map.cell_centre(self.map.start().unwrap_or_default()));
+        self.strokes = 0;
+    }
+
+    /// Hits the ball with `velocity` (pixels per second) and runs the simulation until it stops.
+    /// Does nothing but report if the ball is already in the hole.
+    pub fn shoot(&mut self, velocity: Pos) -> ShotReport {
+        if self.ball.sunk {
+            return self.report(0, 0, 0);
+        }
+        self.ball.vel = velocity;
+        self.strokes += 1;
+
+        let roll = roll(&mut self.ball, &self.map, &mut self.tick);
+        self.strokes += roll.splashes;
+        self.report(roll.wall_hits, roll.steps, roll.splashes)
+    }
+
+    /// Searches for the fewest strokes that get the ball from where it is into the hole.
+    /// `None` if there is no hole or no way in was found within `settings.max_strokes`.
+    pub fn solve(&self, settings: &SolverSettings) -> Option<Solution> {
+        solver::solve(&self.map, &self.ball, self.tick, settings)
+    }
+
+    /// Advances by a single physics step, returning the number of wall hits during it.
+    /// Going in the water adds its penalty stroke straight away.
+    ///
+    /// Moving obstacles keep moving while the ball is at rest, so stepping is also how to wait for one.
+    pub fn step(&mut self) -> u32 {
+        let events = self.ball.update_pos(&self.map, self.tick);
+        self.tick = self.tick.wrapping_add(1);
+        if events.splashed {
+            self.strokes += 1;
+        }
+        events.wall_hits
+    }
+
+    pub fn position(&self) -> Pos {
+        self.ball.pos
+    }
+
+    pub fn is_at_rest(&self) -> bool {
+        self.ball.at_rest()
+    }
+
+    pub fn is_sunk(&self) -> bool {
+        self.ball.sunk
+    }
+
+    pub fn strokes(&self) -> u32 {
+        self.strokes
+    }
+
+    pub fn par(&self) -> u32 {
+        self.map.par
+    }
+
+    fn report(&self, wall_hits: u32, steps: u32, penalty_strokes: u32) -> ShotReport {
+        ShotReport {
+            final_position: self.ball.pos,
+            strokes: self.strokes,
+            wall_hits,
+            sunk: self.ball.sunk,
+            steps,
+            penalty_strokes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::{GameObject, Point, Surface};
+
+    /// a flat 20 by 20 green with the start at (1, 10), plus `objects`
+    fn code(objects: &[GameObject]) -> String {
+        let mut map = GolfMap::default();
+        map.objects = vec![GameObject::Start(Point { x: 1, y: 10 })];
+        map.objects.extend_from_slice(objects);
+        map.to_text()
+    }
+
+    #[test]
+    fn straight_putt_sinks() {
+        let mut sim =
+            Simulation::from_code(&code(&[GameObject::Hole(Point { x: 10, y: 10 })])).unwrap();
+        let report = sim.shoot(Pos::new(275.0, 0.0));
+        assert!(report.sunk, "stopped at {:?}", report.final_position);
+        assert_eq!(report.strokes, 1);
+        assert_eq!(report.wall_hits, 0);
+        assert!(sim.is_sunk());
+    }
+
+    #[test]
+    fn wall_bounce_is_counted() {
+        let wall = GameObject::Wall {
+            a: Point { x: 5, y: 0 },
+            b: Point { x: 5, y: 19 },
+        };
+        let mut sim = Simulation::from_code(&code(&[wall])).unwrap();
+        let report = sim.shoot(Pos::new(200.0, 0.0));
+        assert_eq!(report.wall_hits, 1);
+        assert!(report.final_position.x < 5.0 * 20.0);
+        assert!(!report.sunk);
+    }
+
+    #[test]
+    fn water_adds_a_penalty_and_puts_the_ball_back() {
+        let water = GameObject::Surface {
+            a: Point { x: 5, y: 0 },
+            b: Point { x: 7, y: 19 },
+            surface: Surface::Water,
+        };
+        let mut sim = Simulation::from_code(&code(&[water])).unwrap();
+        let start = sim.position();
+        let report = sim.shoot(Pos::new(400.0, 0.0));
+        assert_eq!(report.penalty_strokes, 1);
+        assert_eq!(report.strokes, 2);
+        assert_eq!(report.final_position, start);
+        assert_eq!(sim.ball.last_rest, start);
+    }
+
+    #[test]
+    fn solved_shots_sink_the_ball_when_played() {
+        let wall = GameObject::Wall {
+            a: Point { x: 9, y: 0 },
+            b: Point { x: 9, y: 16 },
+        };
+        let code = code(&[wall, GameObject::Hole(Point { x: 18, y: 10 })]);
+        let sim = Simulation::from_code(&code).unwrap();
+        let solution = sim
+            .solve(&SolverSettings::default())
+            .expect("a way round the wall");
+        assert_eq!(solution.shots.len() as u32, solution.strokes);
+
+        let mut sim = Simulation::from_code(&code).unwrap();
+        for shot in &solution.shots {
+            assert_eq!(sim.position(), shot.from);
+            sim.shoot(shot.velocity);
+        }
+        assert!(sim.is_sunk());
+        assert_eq!(sim.strokes(), solution.strokes);
+    }
+
+    #[test]
+    fn no_hole_no_solution() {
+        let sim = Simulation::from_code(&code(&[])).unwrap();
+        assert_eq!(sim.solve(&SolverSettings::default()), None);
+    }
+}
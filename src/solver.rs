@@ -0,0 +1,342 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::f32::consts::TAU;
+
+use crate::map::{GameObject, GolfMap, Point, Surface};
+use crate::physics::{GolfBall, Pos, AIM_REACH, AIM_STRENGTH};
+use crate::simulation::roll;
+
+/// How hard the solver looks for a way into the hole. More of everything finds better answers but takes longer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SolverSettings {
+    /// most strokes tried before giving up
+    pub max_strokes: u32,
+    /// directions tried for each shot, evenly spaced all the way round
+    pub angles: u32,
+    /// speeds tried in each direction, evenly spaced up to the hardest shot the game allows
+    pub powers: u32,
+    /// how many of the places the ball stopped are carried on to the next stroke, the closest to the hole first
+    pub beam_width: usize,
+}
+
+impl Default for SolverSettings {
+    fn default() -> Self {
+        Self {
+            max_strokes: 6,
+            angles: 36,
+            powers: 6,
+            beam_width: 12,
+        }
+    }
+}
+
+/// One shot of a solution, taken as soon as the ball has stopped from the one before.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PlannedShot {
+    /// where the ball is hit from
+    pub from: Pos,
+    pub velocity: Pos,
+    /// physics steps since the hole was started when the ball is hit, moving obstacles go by this
+    pub tick: u32,
+}
+
+/// The fewest strokes the solver found to get the ball in the hole, and the shots that do it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Solution {
+    /// strokes taken, penalties for going in the water included
+    pub strokes: u32,
+    pub shots: Vec<PlannedShot>,
+}
+
+/// somewhere the ball has stopped on the way to the hole
+struct Node {
+    ball: GolfBall,
+    tick: u32,
+    strokes: u32,
+    shots: Vec<PlannedShot>,
+}
+
+/// A search for the fewest strokes that get a ball into the hole, done a few shots at a time
+/// so it can be spread over frames without holding the game up.
+///
+/// Every direction and speed in the settings is tried from each place the ball has stopped,
+/// then only the best `beam_width` places (one per cell) are tried from on the next stroke.
+pub(crate) struct Search {
+    settings: SolverSettings,
+    velocities: Vec<Pos>,
+    /// cells the ball has to cross from each cell to the hole
+    distances: HashMap<Point, u32>,
+    cup: Pos,
+    /// the stroke being tried, counting from 1
+    stroke: u32,
+    /// places the ball is being hit from on this stroke
+    beam: Vec<Node>,
+    /// places the ball stopped on this stroke, to be hit from on the next
+    next: Vec<Node>,
+    /// index into `beam` then `velocities` of the next shot to try
+    node: usize,
+    velocity: usize,
+    best: Option<Solution>,
+    /// the first shot towards the closest place the ball has stopped, for when no way in is found
+    closest: Option<PlannedShot>,
+    finished: bool,
+}
+
+impl Search {
+    pub(crate) fn new(
+        map: &GolfMap,
+        ball: &GolfBall,
+        tick: u32,
+        settings: &SolverSettings,
+    ) -> Search {
+        let velocities: Vec<Pos> = (0..settings.angles)
+            .flat_map(|a| (1..=settings.powers).map(move |p| (a, p)))
+            .map(|(a, p)| {
+                let angle = TAU * a as f32 / settings.angles as f32;
+                let speed = AIM_REACH * AIM_STRENGTH * p as f32 / settings.powers as f32;
+                Pos::new(angle.cos(), angle.sin()) * speed
+            })
+            .collect();
+        let hole = map.hole();
+        let nothing_to_try = velocities.is_empty();
+        let mut search = Search {
+            settings: *settings,
+            velocities,
+            distances: hole.map(|h| distances_to(map, h)).unwrap_or_default(),
+            cup: hole.map(|h| map.cell_centre(h)).unwrap_or_default(),
+            stroke: 1,
+            beam: vec![Node {
+                ball: *ball,
+                tick,
+                strokes: 0,
+                shots: Vec::new(),
+            }],
+            next: Vec::new(),
+            node: 0,
+            velocity: 0,
+            best: None,
+            closest: None,
+            finished: hole.is_none() || nothing_to_try,
+        };
+        if ball.sunk && !search.finished {
+            search.best = Some(Solution {
+                strokes: 0,
+                shots: Vec::new(),
+            });
+            search.finished = true;
+        }
+        search
+    }
+
+    /// Tries up to `shots` more shots, giving back the best solution once the search is over.
+    pub(crate) fn run(&mut self, map: &GolfMap, shots: u32) -> Option<Option<Solution>> {
+        for _ in 0..shots {
+            if self.finished {
+                break;
+            }
+            if self.node >= self.beam.len() {
+                self.next_stroke(map);
+                continue;
+            }
+            let node = &self.beam[self.node];
+            let velocity = self.velocities[self.velocity];
+            let mut ball = node.ball;
+            let mut tick = node.tick;
+            ball.vel = velocity;
+            let strokes = node.strokes + 1 + roll(&mut ball, map, &mut tick).splashes;
+            let mut shots = node.shots.clone();
+            shots.push(PlannedShot {
+                from: node.ball.pos,
+                velocity,
+                tick: node.tick,
+            });
+            if ball.sunk {
+                if self.best.as_ref().map_or(true, |b| strokes < b.strokes) {
+                    self.best = Some(Solution { strokes, shots });
+                }
+            } else if strokes < self.settings.max_strokes {
+                self.next.push(Node {
+                    ball,
+                    tick,
+                    strokes,
+                    shots,
+                });
+            }
+            self.velocity += 1;
+            if self.velocity == self.velocities.len() {
+                self.velocity = 0;
+                self.node += 1;
+            }
+        }
+        self.finished.then(|| self.best.clone())
+    }
+
+    /// Carries the best places the ball stopped on to the next stroke, or ends the search.
+    fn next_stroke(&mut self, map: &GolfMap) {
+        // cells the hole can't be reached from at all go to the back
+        let unreachable = (map.width * map.height) as u32;
+        let closeness = |node: &Node| {
+            let cells = self
+                .distances
+                .get(&map.cell_at(node.ball.pos))
+                .copied()
+                .unwrap_or(unreachable);
+            (cells, node.strokes, (node.ball.pos - self.cup).velocity())
+        };
+        let mut next = std::mem::take(&mut self.next);
+        next.sort_by(|a, b| {
+            let (a, b) = (closeness(a), closeness(b));
+            a.0.cmp(&b.0).then(a.1.cmp(&b.1)).then(a.2.total_cmp(&b.2))
+        });
+        let mut seen = HashSet::new();
+        self.beam = next
+            .into_iter()
+            .filter(|n| seen.insert(map.cell_at(n.ball.pos)))
+            .take(self.settings.beam_width)
+            .collect();
+        if let Some(shot) = self.beam.first().and_then(|n| n.shots.first()) {
+            self.closest = Some(*shot);
+        }
+        self.node = 0;
+        self.velocity = 0;
+        self.stroke += 1;
+        // every shot from here on makes at least `stroke` strokes
+        let beaten = self
+            .best
+            .as_ref()
+            .map_or(false, |b| b.strokes <= self.stroke);
+        self.finished = beaten || self.beam.is_empty() || self.stroke > self.settings.max_strokes;
+    }
+
+    /// roughly how far through the search is, from 0 to 1
+    pub(crate) fn progress(&self) -> f32 {
+        if self.finished {
+            return 1.0;
+        }
+        let through_stroke = self.node as f32 / self.beam.len().max(1) as f32;
+        (self.stroke as f32 - 1.0 + through_stroke) / self.settings.max_strokes.max(1) as f32
+    }
+
+    /// The first shot of the best solution, or failing that of the closest the ball got to the hole.
+    pub(crate) fn first_shot(&self) -> Option<PlannedShot> {
+        self.best
+            .as_ref()
+            .and_then(|b| b.shots.first().copied())
+            .or(self.closest)
+    }
+}
+
+/// Searches stroke by stroke for the fewest that get `ball` into the hole, all in one go.
+pub(crate) fn solve(
+    map: &GolfMap,
+    ball: &GolfBall,
+    tick: u32,
+    settings: &SolverSettings,
+) -> Option<Solution> {
+    let mut search = Search::new(map, ball, tick, settings);
+    loop {
+        if let Some(solution) = search.run(map, u32::MAX) {
+            return solution;
+        }
+    }
+}
+
+/// How many cells the ball has to cross to get from each cell to the hole, going round walls and water.
+fn distances_to(map: &GolfMap, hole: Point) -> HashMap<Point, u32> {
+    let open = |p: &Point| {
+        map.contains(p)
+            && !matches!(map.get_point(p), Some(GameObject::Wall { .. }))
+            && map.surface_at(p) != Some(Surface::Water)
+    };
+    let mut distances = HashMap::from([(hole, 0)]);
+    let mut queue = VecDeque::from([hole]);
+    while let Some(point) = queue.pop_front() {
+        let distance = distances[&point];
+        for (dx, dy) in [
+            (-1, -1),
+            (0, -1),
+            (1, -1),
+            (-1, 0),
+            (1, 0),
+            (-1, 1),
+            (0, 1),
+            (1, 1),
+        ] {
+            let next = Point {
+                x: point.x + dx,
+                y: point.y + dy,
+            };
+            if open(&next) && !distances.contains_key(&next) {
+                distances.insert(next, distance + 1);
+                queue.push_back(next);
+            }
+        }
+    }
+    distances
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map() -> GolfMap {
+        let mut map = GolfMap::default();
+        map.objects = vec![
+            GameObject::Start(Point { x: 1, y: 10 }),
+            GameObject::Wall {
+                a: Point { x: 9, y: 4 },
+                b: Point { x: 9, y: 16 },
+            },
+            GameObject::Hole(Point { x: 18, y: 10 }),
+        ];
+        map.update_hashmap();
+        map.update_heightmap();
+        map
+    }
+
+    fn tee(map: &GolfMap) -> GolfBall {
+        let mut ball = GolfBall::default();
+        ball.place(map.cell_centre(map.start().unwrap()));
+        ball
+    }
+
+    #[test]
+    fn searching_a_few_shots_at_a_time_finds_the_same_solution() {
+        let map = map();
+        let settings = SolverSettings {
+            max_strokes: 3,
+            ..SolverSettings::default()
+        };
+        let mut search = Search::new(&map, &tee(&map), 0, &settings);
+        let mut progress = 0.0;
+        let found = loop {
+            if let Some(found) = search.run(&map, 7) {
+                break found;
+            }
+            assert!(search.progress() >= progress);
+            progress = search.progress();
+        };
+        assert_eq!(search.progress(), 1.0);
+        assert!(found.is_some());
+        assert_eq!(found, solve(&map, &tee(&map), 0, &settings));
+    }
+
+    #[test]
+    fn first_shot_falls_back_to_the_closest() {
+        let mut map = map();
+        // wall the hole off completely
+        map.objects[1] = GameObject::Wall {
+            a: Point { x: 9, y: 0 },
+            b: Point { x: 9, y: 19 },
+        };
+        map.update_hashmap();
+        let settings = SolverSettings {
+            max_strokes: 2,
+            beam_width: 4,
+            ..SolverSettings::default()
+        };
+        let mut search = Search::new(&map, &tee(&map), 0, &settings);
+        assert_eq!(search.run(&map, u32::MAX), Some(None));
+        let shot = search.first_shot().expect("somewhere to go");
+        assert_eq!(shot.from, tee(&map).pos);
+    }
+}